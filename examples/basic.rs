@@ -135,13 +135,13 @@ impl App {
             width: 500.0,
             height: 500.0,
             text: "Press a to debug-show the glyph texture atlas, press b to debug-show text area borders. Press d to add a char.".to_string(),
-            font: FontID::Default,
             size: 64.0,
             line_height_factor: 0.8,
             top_offset: 0.0,
             left_offset: 0.0,
             v_align: easytext::VerticalAlign::Middle,
             h_align: easytext::HorizontalAlign::Center,
+            ..TextArea::new(FontID::Default)
         });
 
         Self {
@@ -212,7 +212,7 @@ impl App {
                 Key::Character("a") => self.easy_text.toggle_debug_show_atlas(),
                 Key::Character("b") => self.easy_text.toggle_debug_show_area_borders(),
                 Key::Character("d") => {
-                    let area = self.easy_text.text_area_mut(self.text_area_handle).unwrap();
+                    let mut area = self.easy_text.text_area_mut(self.text_area_handle).unwrap();
                     area.text.push('d');
                 }
                 _ => {}
@@ -0,0 +1,57 @@
+use std::hash::Hash;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::{EasyText, TextAreaHandle, VertexFormat};
+
+/// What a text area is used for, see `TextArea::accessibility_role`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum AccessibilityRole {
+    #[default]
+    Label,
+    Button,
+    /// Heading with its level, starting at 1
+    Heading(u8),
+    Paragraph,
+}
+
+/// The text and pixel bounds of a visible text area, for building an accessibility tree
+/// (e.g. with accesskit).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AccessibilityNode {
+    pub handle: TextAreaHandle,
+    pub text: String,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub role: AccessibilityRole,
+}
+
+impl<F: Eq + Hash + Copy, V: VertexFormat> EasyText<F, V> {
+    /// Returns a node for every visible text area, sorted by handle.
+    pub fn accessibility_nodes(&self) -> Vec<AccessibilityNode> {
+        let mut nodes: Vec<_> = self
+            .text_areas
+            .iter()
+            .filter(|(_, entry)| entry.area.visible)
+            .map(|(handle, entry)| {
+                let area = &entry.area;
+                let [x, y, width, height] = area.pixel_rect(self.window_size);
+                AccessibilityNode {
+                    handle: *handle,
+                    text: area.text.clone(),
+                    x,
+                    y,
+                    width,
+                    height,
+                    role: area.accessibility_role,
+                }
+            })
+            .collect();
+        nodes.sort_unstable_by_key(|node| node.handle);
+        nodes
+    }
+}
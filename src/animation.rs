@@ -0,0 +1,62 @@
+use crate::easing::{self, Easing};
+
+/// A color a text area has `time_secs` seconds after `EasyText::animate_color` was called.
+/// The color is interpolated from the previous keyframe with `easing`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorKeyframe {
+    pub time_secs: f32,
+    pub color: [f32; 4],
+    pub easing: Easing,
+}
+
+// Interpolates `TextArea::opacity`, see `EasyText::animate_fade`
+pub(crate) struct FadeAnimation {
+    pub start_alpha: f32,
+    pub target_alpha: f32,
+    // `EasyText::animation_time` when the fade started
+    pub start_time: f64,
+    pub duration: f64,
+}
+
+impl FadeAnimation {
+    /// Opacity at `time`, and whether the fade is finished.
+    pub fn opacity(&self, time: f64) -> (f32, bool) {
+        let progress = match self.duration > 0.0 {
+            true => ((time - self.start_time) / self.duration).min(1.0) as f32,
+            false => 1.0,
+        };
+        let opacity = self.start_alpha + (self.target_alpha - self.start_alpha) * progress;
+        (opacity, progress >= 1.0)
+    }
+}
+
+// Interpolates `TextArea::color`, see `EasyText::animate_color`
+pub(crate) struct ColorAnimation {
+    // Sorted by time
+    pub keyframes: Vec<ColorKeyframe>,
+    pub start_time: f64,
+}
+
+impl ColorAnimation {
+    /// Color at `time`, and whether the last keyframe was reached.
+    /// Returns `None` if there are no keyframes.
+    pub fn color(&self, time: f64) -> Option<([f32; 4], bool)> {
+        let elapsed = (time - self.start_time) as f32;
+        let next = self
+            .keyframes
+            .iter()
+            .position(|keyframe| keyframe.time_secs > elapsed);
+        let (previous, next) = match next {
+            Some(0) => return Some((self.keyframes[0].color, false)),
+            Some(next) => (&self.keyframes[next - 1], &self.keyframes[next]),
+            None => return self.keyframes.last().map(|last| (last.color, true)),
+        };
+        let t = (elapsed - previous.time_secs) / (next.time_secs - previous.time_secs);
+        let t = easing::apply(next.easing, t);
+        let mut color = previous.color;
+        for (channel, target) in color.iter_mut().zip(next.color) {
+            *channel += (target - *channel) * t;
+        }
+        Some((color, false))
+    }
+}
@@ -1,7 +1,166 @@
-use std::hash::Hash;
+use std::{hash::Hash, ops::Range};
 
 use fontdue::layout::{HorizontalAlign, VerticalAlign};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
 
+use crate::{BlendMode, FontStyle, FontWeight, MaterialHandle};
+
+/// Unit of `x`, `y`, `width` and `height` of a text area, see `TextArea::coordinate_system`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum CoordinateMode {
+    /// x, y, width and height are in pixels
+    #[default]
+    PixelSpace,
+    /// x, y, width and height are fractions ([0, 1]) of the window size
+    Normalized,
+}
+
+/// Which point of the text area `x` and `y` refer to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    TopCenter,
+    TopRight,
+    MiddleLeft,
+    Center,
+    MiddleRight,
+    BottomLeft,
+    BottomCenter,
+    BottomRight,
+}
+
+impl Anchor {
+    // Position of the anchor relative to the area size
+    fn factors(self) -> [f32; 2] {
+        match self {
+            Anchor::TopLeft => [0.0, 0.0],
+            Anchor::TopCenter => [0.5, 0.0],
+            Anchor::TopRight => [1.0, 0.0],
+            Anchor::MiddleLeft => [0.0, 0.5],
+            Anchor::Center => [0.5, 0.5],
+            Anchor::MiddleRight => [1.0, 0.5],
+            Anchor::BottomLeft => [0.0, 1.0],
+            Anchor::BottomCenter => [0.5, 1.0],
+            Anchor::BottomRight => [1.0, 1.0],
+        }
+    }
+}
+
+/// Where lines may be wrapped if the text is wider than the area
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum OverflowWrap {
+    /// Only between words, words longer than the area stick out of it
+    #[default]
+    Normal,
+    /// Between any two letters
+    BreakAll,
+    /// Between words, or between letters if a word is longer than the area.
+    /// The whole text is then wrapped between letters
+    BreakWord,
+}
+
+// fontdue's alignments don't implement serde, so they are mirrored here
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "VerticalAlign")]
+enum VerticalAlignDef {
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "HorizontalAlign")]
+enum HorizontalAlignDef {
+    Left,
+    Center,
+    Right,
+}
+
+/// A copy of the glyphs drawn behind them, see `TextArea::shadow`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TextShadow {
+    pub offset_x: f32,
+    pub offset_y: f32,
+    pub color: [f32; 4],
+    /// Not supported yet and ignored, the shadow always has hard edges
+    pub blur_radius: f32,
+}
+
+/// An outline around the glyphs, see `TextArea::outline`.
+/// Drawn by offsetting copies of the glyphs, so it works best for small widths.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TextOutline {
+    pub width: f32,
+    pub color: [f32; 4],
+}
+
+/// Text an input method editor is composing, see `EasyText::set_ime_composition`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ImeComposition {
+    pub preedit_text: String,
+    /// Byte offset into `preedit_text` the cursor is drawn in front of
+    pub cursor: usize,
+}
+
+/// A border drawn around a text area, see `TextArea::border`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TextAreaBorder {
+    /// Thickness in pixels, the border is drawn outside of the area
+    pub width: f32,
+    pub color: [f32; 4],
+    /// Radius of the outer corners in pixels, 0 for square corners
+    pub radius: f32,
+}
+
+/// Progress of revealing the text one char at a time, see `EasyText::advance_typewriter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct TypewriterState {
+    /// Number of chars (not bytes) at the start of the text that are drawn
+    pub chars_visible: usize,
+    pub total_chars: usize,
+}
+
+impl TypewriterState {
+    /// Starts with no chars of `text` visible.
+    pub fn new(text: &str) -> Self {
+        Self {
+            chars_visible: 0,
+            total_chars: text.chars().count(),
+        }
+    }
+}
+
+/// Moves the glyphs up and down along a sine wave, see `EasyText::tick_animations`.
+/// The glyph with char index `i` is offset by
+/// `amplitude * sin(frequency * speed * time + i * phase_offset_per_char)` pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct WaveAnimation {
+    pub amplitude: f32,
+    pub frequency: f32,
+    /// Multiplier for the animation time, 1.0 plays the wave at its `frequency`
+    pub speed: f32,
+    pub phase_offset_per_char: f32,
+}
+
+/// Returns the byte offsets a word may be hyphenated at, see `TextArea::hyphenation`.
+pub type Hyphenation = Box<dyn Fn(&str) -> Vec<usize> + Send + Sync>;
+
+/// Fonts are serialized as their `F` key only, so they have to be added again before
+/// a deserialized text area can be rendered.
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct TextArea<F: Eq + Hash + Copy> {
     pub x: f32,
     pub y: f32,
@@ -9,10 +168,229 @@ pub struct TextArea<F: Eq + Hash + Copy> {
     pub height: f32,
     pub text: String,
     pub font: F,
+    /// Picks the face of a font family added with `EasyText::add_font_family`
+    pub font_weight: FontWeight,
+    pub font_style: FontStyle,
     pub size: f32,
+    /// Lays the text out at a lower size than `size` whenever the area is rendered until it fits
+    /// into the area, but not below `min_font_size`. `size` itself is left as it is, see
+    /// `GlyphInfo::size` for the size the glyphs were laid out at. Not used with `auto_width`
+    /// or `auto_height`.
+    pub shrink_to_fit: bool,
+    pub min_font_size: f32,
     pub line_height_factor: f32,
+    /// Distance between tab stops in pixels, text behind a `\t` continues at the next one
+    pub tab_width: f32,
+    /// Extra space in pixels between paragraphs, added below every hard line break (`\n`).
+    /// Not used with `vertical_layout`.
+    pub paragraph_spacing: f32,
+    /// Pixels the first line of every paragraph is moved to the right.
+    /// Lines are still wrapped as if they weren't indented. Not used with `vertical_layout`.
+    pub first_line_indent: f32,
+    /// Pixels the text is scrolled up by, glyphs outside of the area are not drawn.
+    /// See `EasyText::text_area_content_height`. Not used with `vertical_layout`.
+    pub scroll_y: f32,
+    /// Draws a thumb of `scrollbar_width` pixels on the right edge of the area that shows
+    /// the visible part of the text, if it is taller than the area. Not used with `vertical_layout`.
+    pub show_scrollbar: bool,
+    pub scrollbar_color: [f32; 4],
+    pub scrollbar_width: f32,
     pub top_offset: f32,
     pub left_offset: f32,
+    #[cfg_attr(feature = "serde", serde(with = "VerticalAlignDef"))]
     pub v_align: VerticalAlign,
+    #[cfg_attr(feature = "serde", serde(with = "HorizontalAlignDef"))]
     pub h_align: HorizontalAlign,
+    pub overflow_wrap: OverflowWrap,
+    /// RGBA color of the glyphs
+    pub color: [f32; 4],
+    pub shadow: Option<TextShadow>,
+    pub outline: Option<TextOutline>,
+    /// Multiplies the alpha of everything drawn for the area, see `EasyText::animate_fade`
+    pub opacity: f32,
+    /// Depth of the glyphs in clip space ([0, 1]), only used if depth testing is enabled
+    pub z_depth: f32,
+    /// Column-major matrix applied to the glyph positions (in pixels) before they are drawn,
+    /// around `transform_origin`
+    pub transform: Option<[[f32; 4]; 4]>,
+    /// Point of the area `transform` rotates and scales around, relative to its size.
+    /// [0.5, 0.5] is the center, [0.0, 0.0] the top left corner.
+    pub transform_origin: [f32; 2],
+    pub coordinate_system: CoordinateMode,
+    pub anchor: Anchor,
+    /// If set, `width` / `height` are updated to the size of the laid out text whenever the area is rendered
+    pub auto_width: bool,
+    pub auto_height: bool,
+    /// Byte range into `text` that is highlighted with `selection_color`
+    pub selection: Option<Range<usize>>,
+    pub selection_color: [f32; 4],
+    /// Byte ranges into `text` drawn with a background color, below the selection.
+    /// They are not updated when `text` changes. See `EasyText::set_highlight_pattern`.
+    pub highlights: Vec<(Range<usize>, [f32; 4])>,
+    /// Lines of `text` (counted by line breaks, not wrapping) drawn with a background color
+    /// across the full width of the area, below the highlights. E.g. the line of the cursor.
+    pub highlighted_lines: Vec<(usize, [f32; 4])>,
+    /// Byte offset into `text` the cursor is drawn in front of
+    pub cursor_position: Option<usize>,
+    pub cursor_color: [f32; 4],
+    pub cursor_width: f32,
+    /// Text being composed with an input method editor, shown at the cursor with a dotted
+    /// underline in `ime_color`. Not shown with `vertical_layout`.
+    pub ime_composition: Option<ImeComposition>,
+    pub ime_color: [f32; 4],
+    /// Drawn around the pixel rect of the area, below the text. Not affected by `transform`.
+    pub border: Option<TextAreaBorder>,
+    /// Clips the text, selection and highlights to the area with corners of this radius in
+    /// pixels, like CSS border-radius. 0 doesn't clip. Custom shaders have to apply it themselves.
+    pub corner_radius: f32,
+    /// Invisible areas keep their layout, but are not drawn
+    pub visible: bool,
+    /// Only draws the first chars of the text, the layout doesn't change while they are revealed
+    pub typewriter: Option<TypewriterState>,
+    /// The area is laid out again every `EasyText::tick_animations` while it has a wave
+    pub wave_animation: Option<WaveAnimation>,
+    /// Groups areas, see `EasyText::set_layer_visible` and `EasyText::remove_layer`
+    pub layer: u32,
+    /// Overrides the blend mode set with `EasyTextBuilder::blend_mode` for this area,
+    /// e.g. `BlendMode::Additive` for glowing text
+    pub blend_mode: Option<BlendMode>,
+    /// Material registered with `EasyText::register_material`, replaces `blend_mode`
+    pub material: Option<MaterialHandle>,
+    /// Lays out the text top to bottom in columns going from right to left, e.g. for Japanese.
+    /// Glyphs are drawn upright and `v_align` / `h_align` are ignored.
+    pub vertical_layout: bool,
+    /// Called with a word that is wrapped onto the next line, returns the byte offsets in the word
+    /// it may be hyphenated at. Not used together with `vertical_layout`, `bidi` or `auto_width`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub hyphenation: Option<Hyphenation>,
+    /// Reorders right-to-left text into visual order before it is laid out.
+    /// Byte offsets (selection, cursor) still refer to the logical order of `text`.
+    #[cfg(feature = "bidi")]
+    pub bidi: bool,
+    /// How screen readers should treat the text, see `EasyText::accessibility_nodes`
+    #[cfg(feature = "accessibility")]
+    pub accessibility_role: crate::AccessibilityRole,
+}
+
+impl<F: Eq + Hash + Copy> TextArea<F> {
+    /// Creates an empty, zero sized text area using `font`, with all other fields set to their defaults.
+    /// Meant to be used with struct update syntax: `TextArea { x: 10.0, ..TextArea::new(font) }`
+    pub fn new(font: F) -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
+            text: String::new(),
+            font,
+            font_weight: crate::family::DEFAULT_FONT_WEIGHT,
+            font_style: FontStyle::Normal,
+            size: 16.0,
+            shrink_to_fit: false,
+            min_font_size: 8.0,
+            line_height_factor: 1.0,
+            tab_width: 64.0,
+            paragraph_spacing: 0.0,
+            first_line_indent: 0.0,
+            scroll_y: 0.0,
+            show_scrollbar: false,
+            scrollbar_color: [0.5, 0.5, 0.5, 0.8],
+            scrollbar_width: 4.0,
+            top_offset: 0.0,
+            left_offset: 0.0,
+            v_align: VerticalAlign::Top,
+            h_align: HorizontalAlign::Left,
+            overflow_wrap: OverflowWrap::Normal,
+            color: [1.0, 1.0, 1.0, 1.0],
+            shadow: None,
+            outline: None,
+            opacity: 1.0,
+            z_depth: 0.0,
+            transform: None,
+            transform_origin: [0.5, 0.5],
+            coordinate_system: CoordinateMode::PixelSpace,
+            anchor: Anchor::TopLeft,
+            auto_width: false,
+            auto_height: false,
+            selection: None,
+            selection_color: [0.2, 0.4, 0.9, 0.5],
+            highlights: Vec::new(),
+            highlighted_lines: Vec::new(),
+            cursor_position: None,
+            cursor_color: [1.0, 1.0, 1.0, 1.0],
+            cursor_width: 2.0,
+            ime_composition: None,
+            ime_color: [0.6, 0.8, 1.0, 1.0],
+            border: None,
+            corner_radius: 0.0,
+            visible: true,
+            typewriter: None,
+            wave_animation: None,
+            layer: 0,
+            blend_mode: None,
+            material: None,
+            vertical_layout: false,
+            hyphenation: None,
+            #[cfg(feature = "bidi")]
+            bidi: false,
+            #[cfg(feature = "accessibility")]
+            accessibility_role: crate::AccessibilityRole::Label,
+        }
+    }
+
+    /// Returns `[x, y, width, height]` of the area in pixels, with `x` and `y` being the top left corner.
+    pub fn pixel_rect(&self, window_size: [u32; 2]) -> [f32; 4] {
+        let [x, y, width, height] = match self.coordinate_system {
+            CoordinateMode::PixelSpace => [self.x, self.y, self.width, self.height],
+            CoordinateMode::Normalized => {
+                let [window_width, window_height] = window_size.map(|size| size as f32);
+                [
+                    self.x * window_width,
+                    self.y * window_height,
+                    self.width * window_width,
+                    self.height * window_height,
+                ]
+            }
+        };
+        let [anchor_x, anchor_y] = self.anchor.factors();
+        [x - width * anchor_x, y - height * anchor_y, width, height]
+    }
+
+    // Sets the given dimensions, which are in pixels, converting them to the coordinate system of the area
+    pub(crate) fn set_pixel_size(
+        &mut self,
+        width: Option<f32>,
+        height: Option<f32>,
+        window_size: [u32; 2],
+    ) {
+        let [window_width, window_height] = match self.coordinate_system {
+            CoordinateMode::PixelSpace => [1.0, 1.0],
+            CoordinateMode::Normalized => window_size.map(|size| size as f32),
+        };
+        if let Some(width) = width {
+            self.width = width / window_width;
+        }
+        if let Some(height) = height {
+            self.height = height / window_height;
+        }
+    }
+
+    /// Sets `transform` to a 2D affine transform that scales, then rotates by `rotation` radians
+    /// (clockwise, as y points down) and finally translates the text, all in pixels.
+    /// Scaling and rotation happen around `transform_origin`.
+    pub fn with_transform_2d(
+        mut self,
+        translation: [f32; 2],
+        rotation: f32,
+        scale: [f32; 2],
+    ) -> Self {
+        let (sin, cos) = rotation.sin_cos();
+        self.transform = Some([
+            [cos * scale[0], sin * scale[0], 0.0, 0.0],
+            [-sin * scale[1], cos * scale[1], 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [translation[0], translation[1], 0.0, 1.0],
+        ]);
+        self
+    }
 }
@@ -1,15 +1,25 @@
-use std::hash::{BuildHasherDefault, Hash};
+use std::{
+    hash::{BuildHasherDefault, Hash},
+    io::{Read, Write},
+};
 
-use ahash::AHasher;
-use etagere::{size2, Allocation, BucketedAtlasAllocator};
-use fontdue::Metrics;
+use ahash::{AHasher, HashMap};
+use etagere::{
+    point2, size2, AllocId, Allocation, AtlasAllocator, BucketedAtlasAllocator, Rectangle, Size,
+};
+use fontdue::{Metrics, OutlineBounds};
 use lru::LruCache;
 use wgpu::{
-    AddressMode, BindGroup, BindGroupLayout, Device, Extent3d, FilterMode, Queue, Sampler,
-    SamplerDescriptor, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
-    TextureView, TextureViewDescriptor,
+    AddressMode, BindGroup, BindGroupLayout, Device, Extent3d, FilterMode, Queue, RenderPipeline,
+    Sampler, SamplerDescriptor, Texture, TextureDescriptor, TextureDimension, TextureFormat,
+    TextureUsages, TextureViewDescriptor,
 };
 
+use crate::error::EasyTextError;
+
+const CACHE_MAGIC: &[u8; 4] = b"ETAC";
+const CACHE_VERSION: u32 = 1;
+
 #[derive(Debug, Clone)]
 pub struct PreparedGlyph {
     pub metrics: Metrics,
@@ -18,24 +28,221 @@ pub struct PreparedGlyph {
     bitmap: Vec<u8>,
 }
 
-pub struct Atlas<F: Eq + Hash + Copy> {
-    pub size: u32,
-    max_size: u32,
-    allocator: BucketedAtlasAllocator,
+/// How glyphs are packed into the atlas, see `EasyTextBuilder::with_atlas_packer`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AtlasPacker {
+    /// Shelves split into buckets of similar sized glyphs (`etagere::BucketedAtlasAllocator`).
+    /// Fast and reuses the space of evicted glyphs well for the usual mix of glyph sizes.
+    #[default]
+    Bucketed,
+    /// Shelves of free rectangles that are merged again when glyphs are evicted
+    /// (`etagere::AtlasAllocator`). Slower, but fragments the least with many different sizes.
+    Shelf,
+    /// Glyphs are placed left to right in rows, a new row starts below the tallest glyph.
+    /// The fastest and tightest for glyphs of the same size, but the space of evicted glyphs
+    /// is only reused once all glyphs of the page were evicted.
+    Row,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AtlasSettings {
+    pub initial_size: u32,
+    pub max_size: u32,
+    pub per_font_atlas: bool,
+    pub use_mipmaps: bool,
+    pub anisotropy_clamp: Option<u16>,
+    pub packer: AtlasPacker,
+    pub glyph_padding: u32,
+    pub gamma_correct: bool,
+    pub size_quantization: f32,
+}
+
+impl Default for AtlasSettings {
+    fn default() -> Self {
+        Self {
+            initial_size: 512,
+            max_size: 8192,
+            per_font_atlas: false,
+            use_mipmaps: false,
+            anisotropy_clamp: None,
+            packer: AtlasPacker::default(),
+            glyph_padding: 1,
+            gamma_correct: false,
+            size_quantization: 1.0,
+        }
+    }
+}
+
+// Places glyphs left to right in rows, see `AtlasPacker::Row`
+struct RowAllocator {
+    size: Size,
+    // Top left corner of the next glyph
+    cursor: [i32; 2],
+    row_height: i32,
+    next_id: u32,
+    allocation_count: u32,
+}
+
+impl RowAllocator {
+    fn new(size: Size) -> Self {
+        Self {
+            size,
+            cursor: [0, 0],
+            row_height: 0,
+            next_id: 0,
+            allocation_count: 0,
+        }
+    }
+
+    fn allocate(&mut self, size: Size) -> Option<Allocation> {
+        if size.width > self.size.width {
+            return None;
+        }
+        let [mut x, mut y] = self.cursor;
+        if x + size.width > self.size.width {
+            x = 0;
+            y += self.row_height;
+            self.row_height = 0;
+        }
+        if y + size.height > self.size.height {
+            return None;
+        }
+        self.cursor = [x + size.width, y];
+        self.row_height = self.row_height.max(size.height);
+        self.next_id = self.next_id.wrapping_add(1);
+        self.allocation_count += 1;
+        Some(Allocation {
+            id: AllocId::deserialize(self.next_id),
+            rectangle: Rectangle {
+                min: point2(x, y),
+                max: point2(x + size.width, y + size.height),
+            },
+        })
+    }
+
+    // The space is only reused once everything was deallocated
+    fn deallocate(&mut self) {
+        self.allocation_count -= 1;
+        if self.allocation_count == 0 {
+            *self = Self::new(self.size);
+        }
+    }
+}
+
+enum PageAllocator {
+    Bucketed(BucketedAtlasAllocator),
+    Shelf(AtlasAllocator),
+    Row(RowAllocator),
+}
+
+impl PageAllocator {
+    fn new(packer: AtlasPacker, size: u32) -> Self {
+        let size = size2(size as i32, size as i32);
+        match packer {
+            AtlasPacker::Bucketed => PageAllocator::Bucketed(BucketedAtlasAllocator::new(size)),
+            AtlasPacker::Shelf => PageAllocator::Shelf(AtlasAllocator::new(size)),
+            AtlasPacker::Row => PageAllocator::Row(RowAllocator::new(size)),
+        }
+    }
+
+    fn allocate(&mut self, size: Size) -> Option<Allocation> {
+        match self {
+            PageAllocator::Bucketed(allocator) => allocator.allocate(size),
+            PageAllocator::Shelf(allocator) => allocator.allocate(size),
+            PageAllocator::Row(allocator) => allocator.allocate(size),
+        }
+    }
+
+    fn deallocate(&mut self, id: AllocId) {
+        match self {
+            PageAllocator::Bucketed(allocator) => allocator.deallocate(id),
+            PageAllocator::Shelf(allocator) => allocator.deallocate(id),
+            PageAllocator::Row(allocator) => allocator.deallocate(),
+        }
+    }
+}
+
+// Glyphs are stored in one page, or one page per font if `per_font_atlas` is enabled
+struct AtlasPage<F: Eq + Hash + Copy> {
+    size: u32,
+    allocator: PageAllocator,
     // (FontId, Size, GlyphKey) -> PreparedGlyph
     allocated: LruCache<(F, u16, u16), PreparedGlyph>,
     texture: Texture,
-    texture_view: TextureView,
+    texture_bind_group: BindGroup,
+    // Set whenever level 0 changed and the other mip levels have to be regenerated
+    mipmaps_dirty: bool,
+}
+
+// Everything the pages need to (re)create their textures
+struct PageResources {
+    max_size: u32,
+    packer: AtlasPacker,
+    // Empty pixels around every glyph, so linear filtering doesn't pick up its neighbours
+    glyph_padding: u32,
+    // R8Unorm, or Rgba8Unorm with the coverage of every subpixel, see `subpixel_enabled`
+    format: TextureFormat,
+    // Maps the coverage to the bytes written to the atlas, see `gamma_table`
+    gamma_table: Option<[u8; 256]>,
     texture_sampler: Sampler,
-    pub texture_bind_group_layout: BindGroupLayout,
-    pub texture_bind_group: BindGroup,
+    texture_bind_group_layout: BindGroupLayout,
+    mipmap_pipeline: Option<RenderPipeline>,
 }
 
-impl<F: Eq + Hash + Copy> Atlas<F> {
-    pub fn new(device: &Device) -> Self {
-        let size = 512.min(device.limits().max_texture_dimension_2d);
-        let max_size = 8192.min(device.limits().max_texture_dimension_2d);
+pub struct Atlas<F: Eq + Hash + Copy> {
+    initial_size: u32,
+    size_quantization: f32,
+    per_font_atlas: bool,
+    pages: HashMap<Option<F>, AtlasPage<F>>,
+    resources: PageResources,
+}
+
+impl PageResources {
+    // Space a glyph takes up in the atlas, including the padding on all sides
+    fn allocation_size(&self, metrics: &Metrics) -> Size {
+        let padding = 2 * self.glyph_padding as i32;
+        size2(
+            metrics.width as i32 + padding,
+            metrics.height as i32 + padding,
+        )
+    }
+
+    fn bytes_per_pixel(&self) -> u32 {
+        self.format.block_copy_size(None).unwrap()
+    }
 
+    fn mip_level_count(&self, size: u32) -> u32 {
+        match self.mipmap_pipeline {
+            Some(_) => u32::BITS - size.leading_zeros(),
+            None => 1,
+        }
+    }
+}
+
+impl<F: Eq + Hash + Copy> AtlasPage<F> {
+    fn new(device: &Device, size: u32, resources: &PageResources) -> Self {
+        let (texture, texture_bind_group) = Self::create_texture(device, size, resources);
+        Self {
+            size,
+            allocator: PageAllocator::new(resources.packer, size),
+            allocated: LruCache::unbounded_with_hasher(BuildHasherDefault::<AHasher>::default()),
+            texture,
+            texture_bind_group,
+            mipmaps_dirty: false,
+        }
+    }
+
+    fn create_texture(
+        device: &Device,
+        size: u32,
+        resources: &PageResources,
+    ) -> (Texture, BindGroup) {
+        // COPY_SRC for reading the atlas back in `export_png`
+        let mut usage =
+            TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST | TextureUsages::COPY_SRC;
+        if resources.mipmap_pipeline.is_some() {
+            usage |= TextureUsages::RENDER_ATTACHMENT;
+        }
         let texture = device.create_texture(&TextureDescriptor {
             label: Some("EasyText Glyph Atlas Texture"),
             size: Extent3d {
@@ -43,21 +250,162 @@ impl<F: Eq + Hash + Copy> Atlas<F> {
                 height: size,
                 depth_or_array_layers: 1,
             },
-            mip_level_count: 1,
+            mip_level_count: resources.mip_level_count(size),
             sample_count: 1,
             dimension: TextureDimension::D2,
-            format: TextureFormat::R8Unorm,
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+            format: resources.format,
+            usage,
             view_formats: &[],
         });
         let texture_view = texture.create_view(&TextureViewDescriptor::default());
+        let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &resources.texture_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&resources.texture_sampler),
+                },
+            ],
+            label: Some("EasyText Glyph Atlas Texture Bind Group"),
+        });
+        (texture, texture_bind_group)
+    }
+
+    fn grow(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        resources: &PageResources,
+    ) -> Result<(), ()> {
+        let size = (self.size * 2).min(resources.max_size);
+        if self.size == size {
+            return Err(());
+        }
+        self.size = size;
+        self.allocator = PageAllocator::new(resources.packer, size);
+
+        // Create new texture
+        let (texture, texture_bind_group) = Self::create_texture(device, size, resources);
+        // Copy all glyphs to new texture
+        for (_, glyph) in &mut self.allocated {
+            if glyph.metrics.width == 0 || glyph.metrics.height == 0 {
+                continue;
+            }
+            let allocation = self
+                .allocator
+                .allocate(resources.allocation_size(&glyph.metrics))
+                .unwrap();
+            write_glyph(
+                queue,
+                &texture,
+                &allocation,
+                &glyph.metrics,
+                &glyph.bitmap,
+                resources,
+            );
+            glyph.allocation = Some(allocation);
+        }
+        self.texture = texture;
+        self.texture_bind_group = texture_bind_group;
+        self.mipmaps_dirty = true;
+        Ok(())
+    }
+
+    // Renders every mip level from the level above it
+    fn generate_mipmaps(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        resources: &PageResources,
+        pipeline: &RenderPipeline,
+    ) {
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("EasyText Atlas Mipmap Encoder"),
+        });
+        let views: Vec<_> = (0..self.texture.mip_level_count())
+            .map(|mip_level| {
+                self.texture.create_view(&TextureViewDescriptor {
+                    label: Some("EasyText Atlas Mipmap View"),
+                    base_mip_level: mip_level,
+                    mip_level_count: Some(1),
+                    ..Default::default()
+                })
+            })
+            .collect();
+        for target in 1..views.len() {
+            let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+                layout: &resources.texture_bind_group_layout,
+                entries: &[
+                    wgpu::BindGroupEntry {
+                        binding: 0,
+                        resource: wgpu::BindingResource::TextureView(&views[target - 1]),
+                    },
+                    wgpu::BindGroupEntry {
+                        binding: 1,
+                        resource: wgpu::BindingResource::Sampler(&resources.texture_sampler),
+                    },
+                ],
+                label: Some("EasyText Atlas Mipmap Bind Group"),
+            });
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("EasyText Atlas Mipmap Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &views[target],
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..4, 0..1);
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        self.mipmaps_dirty = false;
+    }
+}
+
+impl<F: Eq + Hash + Copy> Atlas<F> {
+    /// If `per_font_atlas` is set, every font gets its own texture, so glyphs of one font
+    /// can never be evicted to make room for glyphs of another font.
+    pub fn new(device: &Device, settings: &AtlasSettings) -> Self {
+        let max_dimension = device.limits().max_texture_dimension_2d;
+        let size = settings
+            .initial_size
+            .clamp(64, max_dimension.max(64))
+            .next_power_of_two()
+            .min(max_dimension);
+        let max_size = settings.max_size.min(max_dimension).max(size);
+
+        // Anisotropic filtering requires linear filtering for all filters,
+        // distance fields are interpolated when they are scaled
+        let filter = match settings.use_mipmaps
+            || settings.anisotropy_clamp.is_some()
+            || cfg!(feature = "sdf")
+        {
+            true => FilterMode::Linear,
+            false => FilterMode::Nearest,
+        };
         let texture_sampler = device.create_sampler(&SamplerDescriptor {
             address_mode_u: AddressMode::ClampToEdge,
             address_mode_v: AddressMode::ClampToEdge,
             address_mode_w: AddressMode::ClampToEdge,
-            mag_filter: FilterMode::Nearest,
-            min_filter: FilterMode::Nearest,
-            mipmap_filter: FilterMode::Nearest,
+            mag_filter: filter,
+            min_filter: filter,
+            mipmap_filter: filter,
+            // wgpu silently falls back to 1 if the adapter doesn't support anisotropic filtering
+            anisotropy_clamp: settings
+                .anisotropy_clamp
+                .map_or(1, |clamp| clamp.clamp(1, 16)),
             ..Default::default()
         });
         let texture_bind_group_layout =
@@ -82,119 +430,139 @@ impl<F: Eq + Hash + Copy> Atlas<F> {
                 ],
                 label: Some("EasyText Glyph Atlas Texture Bind Group Layout"),
             });
-        let texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&texture_sampler),
-                },
-            ],
-            label: Some("EasyText Glyph Atlas Texture Bind Group"),
-        });
-
-        Self {
-            size,
+        // Linear, the coverage is blended like the alpha of grayscale glyphs
+        let format = match subpixel_enabled(device) {
+            true => TextureFormat::Rgba8Unorm,
+            false => TextureFormat::R8Unorm,
+        };
+        let mipmap_pipeline = settings
+            .use_mipmaps
+            .then(|| create_mipmap_pipeline(device, &texture_bind_group_layout, format));
+        let resources = PageResources {
             max_size,
-            allocator: BucketedAtlasAllocator::new(size2(size as i32, size as i32)),
-            allocated: LruCache::unbounded_with_hasher(BuildHasherDefault::<AHasher>::default()),
-            texture,
-            texture_view,
+            packer: settings.packer,
+            glyph_padding: settings.glyph_padding,
+            format,
+            gamma_table: (settings.gamma_correct && !cfg!(feature = "sdf")).then(gamma_table),
             texture_sampler,
             texture_bind_group_layout,
-            texture_bind_group,
+            mipmap_pipeline,
+        };
+
+        let mut pages = HashMap::default();
+        if !settings.per_font_atlas {
+            pages.insert(None, AtlasPage::new(device, size, &resources));
+        }
+
+        Self {
+            initial_size: size,
+            size_quantization: settings.size_quantization,
+            per_font_atlas: settings.per_font_atlas,
+            pages,
+            resources,
         }
     }
 
-    fn grow(&mut self, device: &Device, queue: &Queue) -> Result<(), ()> {
-        let size = (self.size * 2).min(self.max_size);
-        if self.size == size {
-            return Err(());
+    pub fn texture_bind_group_layout(&self) -> &BindGroupLayout {
+        &self.resources.texture_bind_group_layout
+    }
+
+    /// Regenerates the mip levels of all pages that changed since the last call.
+    /// Does nothing if mipmaps are disabled.
+    pub fn update_mipmaps(&mut self, device: &Device, queue: &Queue) {
+        let Some(pipeline) = &self.resources.mipmap_pipeline else {
+            return;
+        };
+        for page in self.pages.values_mut() {
+            if page.mipmaps_dirty {
+                page.generate_mipmaps(device, queue, &self.resources, pipeline);
+            }
         }
-        self.size = size;
-        self.allocator.clear();
-        self.allocator.grow(size2(size as i32, size as i32));
+    }
 
-        // Create new texture
-        let texture = device.create_texture(&TextureDescriptor {
-            label: Some("EasyText Glyph Atlas Texture"),
-            size: Extent3d {
-                width: size,
-                height: size,
-                depth_or_array_layers: 1,
-            },
-            mip_level_count: 1,
-            sample_count: 1,
-            dimension: TextureDimension::D2,
-            format: TextureFormat::R8Unorm,
-            usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
-            view_formats: &[],
-        });
-        let texture_view = texture.create_view(&TextureViewDescriptor::default());
-        self.texture_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &self.texture_bind_group_layout,
-            entries: &[
-                wgpu::BindGroupEntry {
-                    binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
-                },
-                wgpu::BindGroupEntry {
-                    binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&self.texture_sampler),
-                },
-            ],
-            label: Some("EasyText Glyph Atlas Texture Bind Group"),
-        });
-        // Copy all glyphs to new texture
-        for (_, glyph) in &mut self.allocated {
-            if glyph.metrics.width == 0 || glyph.metrics.height == 0 {
-                continue;
+    fn page_key(&self, font_id: F) -> Option<F> {
+        self.per_font_atlas.then_some(font_id)
+    }
+
+    fn page_mut(&mut self, device: &Device, font_id: F) -> &mut AtlasPage<F> {
+        let key = self.page_key(font_id);
+        self.pages
+            .entry(key)
+            .or_insert_with(|| AtlasPage::new(device, self.initial_size, &self.resources))
+    }
+
+    /// Creates the page the glyphs of `font_id` are stored in if it doesn't exist yet, so its
+    /// bind group is there for areas without any visible glyph.
+    pub fn ensure_page(&mut self, device: &Device, font_id: F) {
+        self.page_mut(device, font_id);
+    }
+
+    /// Whether glyphs have to be rasterized with the coverage of every subpixel as RGBA.
+    #[cfg(not(feature = "sdf"))]
+    pub fn subpixel(&self) -> bool {
+        self.resources.format == TextureFormat::Rgba8Unorm
+    }
+
+    /// Rounds `size` to the nearest multiple of the size quantization, glyphs are rasterized
+    /// at and keyed by that size so close sizes share their atlas entries.
+    pub fn quantize_size(&self, size: f32) -> f32 {
+        let quantization = self.size_quantization.max(f32::EPSILON);
+        ((size / quantization).round() * quantization).max(1.0)
+    }
+
+    /// Empty pixels between the allocation of a glyph and its bitmap on every side.
+    pub fn glyph_padding(&self) -> u32 {
+        self.resources.glyph_padding
+    }
+
+    /// Width and height of the texture the glyphs of `font_id` are stored in.
+    pub fn page_size(&self, font_id: F) -> u32 {
+        self.pages
+            .get(&self.page_key(font_id))
+            .map_or(self.initial_size, |page| page.size)
+    }
+
+    /// Evicts all glyphs of the fonts `remove` returns true for, and the pages of those
+    /// fonts with `per_font_atlas`.
+    pub fn remove_fonts(&mut self, remove: impl Fn(F) -> bool) {
+        self.pages
+            .retain(|&key, _| key.is_none_or(|font_id| !remove(font_id)));
+        for page in self.pages.values_mut() {
+            let keys: Vec<_> = page
+                .allocated
+                .iter()
+                .map(|(&key, _)| key)
+                .filter(|&(font_id, _, _)| remove(font_id))
+                .collect();
+            for key in keys {
+                let glyph = page.allocated.pop(&key).unwrap();
+                if let Some(allocation) = glyph.allocation {
+                    page.allocator.deallocate(allocation.id);
+                }
             }
-            let allocation = self
-                .allocator
-                .allocate(size2(
-                    glyph.metrics.width as i32,
-                    glyph.metrics.height as i32,
-                ))
-                .unwrap();
-            queue.write_texture(
-                wgpu::ImageCopyTexture {
-                    texture: &texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d {
-                        x: allocation.rectangle.min.x as u32,
-                        y: allocation.rectangle.min.y as u32,
-                        z: 0,
-                    },
-                    aspect: wgpu::TextureAspect::All,
-                },
-                &glyph.bitmap,
-                wgpu::ImageDataLayout {
-                    offset: 0,
-                    bytes_per_row: Some(glyph.metrics.width as u32),
-                    rows_per_image: None,
-                },
-                wgpu::Extent3d {
-                    width: glyph.metrics.width as u32,
-                    height: glyph.metrics.height as u32,
-                    depth_or_array_layers: 1,
-                },
-            );
-            glyph.allocation = Some(allocation);
         }
-        self.texture = texture;
-        self.texture_view = texture_view;
-        Ok(())
+    }
+
+    /// Bind group of the texture the glyphs of `font_id` are stored in.
+    pub fn bind_group(&self, font_id: F) -> Option<&BindGroup> {
+        self.pages
+            .get(&self.page_key(font_id))
+            .map(|page| &page.texture_bind_group)
+    }
+
+    pub fn bind_groups(&self) -> impl Iterator<Item = &BindGroup> {
+        self.pages.values().map(|page| &page.texture_bind_group)
     }
 
     pub fn get(&mut self, font_id: F, size: u16, glyph_index: u16) -> Option<&PreparedGlyph> {
-        self.allocated.get(&(font_id, size, glyph_index))
+        let key = self.page_key(font_id);
+        self.pages
+            .get_mut(&key)?
+            .allocated
+            .get(&(font_id, size, glyph_index))
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn insert(
         &mut self,
         device: &Device,
@@ -205,71 +573,412 @@ impl<F: Eq + Hash + Copy> Atlas<F> {
         metrics: Metrics,
         bitmap: Vec<u8>,
     ) -> &PreparedGlyph {
+        let key = self.page_key(font_id);
+        self.page_mut(device, font_id);
+        let page = self.pages.get_mut(&key).unwrap();
         // Invisible character
         if metrics.width == 0 || metrics.height == 0 {
-            self.allocated.put(
-                (font_id.clone(), size, glyph_index),
+            page.allocated.put(
+                (font_id, size, glyph_index),
                 PreparedGlyph {
                     metrics,
                     allocation: None,
                     bitmap,
                 },
             );
-            return self.allocated.get(&(font_id, size, glyph_index)).unwrap();
+            return page.allocated.get(&(font_id, size, glyph_index)).unwrap();
         }
         // Visible character
         let allocation = loop {
-            match self
+            match page
                 .allocator
-                .allocate(size2(metrics.width as i32, metrics.height as i32))
+                .allocate(self.resources.allocation_size(&metrics))
             {
                 Some(allocation) => {
                     break allocation;
                 }
                 None => {
-                    if self.grow(device, queue).is_err() {
-                        let Some(to_remove) = self.allocated.pop_lru() else {
+                    if page.grow(device, queue, &self.resources).is_err() {
+                        let Some(to_remove) = page.allocated.pop_lru() else {
                             panic!("Failed to allocate glyph");
                         };
                         if let Some(allocation) = to_remove.1.allocation {
-                            self.allocator.deallocate(allocation.id);
+                            page.allocator.deallocate(allocation.id);
                         }
                     }
                 }
             }
         };
-        queue.write_texture(
-            wgpu::ImageCopyTexture {
-                texture: &self.texture,
-                mip_level: 0,
-                origin: wgpu::Origin3d {
-                    x: allocation.rectangle.min.x as u32,
-                    y: allocation.rectangle.min.y as u32,
-                    z: 0,
-                },
-                aspect: wgpu::TextureAspect::All,
-            },
+        write_glyph(
+            queue,
+            &page.texture,
+            &allocation,
+            &metrics,
             &bitmap,
-            wgpu::ImageDataLayout {
-                offset: 0,
-                bytes_per_row: Some(metrics.width as u32),
-                rows_per_image: None,
-            },
-            wgpu::Extent3d {
-                width: metrics.width as u32,
-                height: metrics.height as u32,
-                depth_or_array_layers: 1,
-            },
+            &self.resources,
         );
+        page.mipmaps_dirty = true;
 
-        self.allocated.put(
-            (font_id.clone(), size, glyph_index),
+        page.allocated.put(
+            (font_id, size, glyph_index),
             PreparedGlyph {
                 metrics,
                 allocation: Some(allocation),
                 bitmap,
             },
         );
-        self.allocated.get(&(font_id, size, glyph_index)).unwrap()
+        page.allocated.get(&(font_id, size, glyph_index)).unwrap()
+    }
+
+    /// Copies all pages to the CPU, side by side from left to right, and returns the
+    /// R8 pixels (RGBA8 with subpixel rendering) with the size of the image.
+    /// Blocks until the GPU finished all submitted work.
+    #[cfg(any(feature = "export", feature = "testing"))]
+    pub fn read_pixels_cpu(&self, device: &Device, queue: &Queue) -> (Vec<u8>, [u32; 2]) {
+        let pages: Vec<_> = self.pages.values().collect();
+        let width: u32 = pages.iter().map(|page| page.size).sum();
+        let height = pages.iter().map(|page| page.size).max().unwrap_or(0);
+        let bytes_per_pixel = self.resources.bytes_per_pixel() as usize;
+        let mut image = vec![0; width as usize * height as usize * bytes_per_pixel];
+        let mut page_x = 0;
+        for page in pages {
+            let size = page.size;
+            let row_size = size as usize * bytes_per_pixel;
+            let bytes_per_row =
+                (row_size as u32).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+            let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("EasyText Atlas Readback Buffer"),
+                size: bytes_per_row as u64 * size as u64,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("EasyText Atlas Readback Encoder"),
+            });
+            encoder.copy_texture_to_buffer(
+                page.texture.as_image_copy(),
+                wgpu::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: wgpu::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: Some(bytes_per_row),
+                        rows_per_image: None,
+                    },
+                },
+                Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+            );
+            queue.submit(Some(encoder.finish()));
+            let slice = buffer.slice(..);
+            slice.map_async(wgpu::MapMode::Read, |result| {
+                result.expect("Failed to map the atlas readback buffer")
+            });
+            device.poll(wgpu::Maintain::Wait);
+            let data = slice.get_mapped_range();
+            for (y, row) in data.chunks(bytes_per_row as usize).enumerate() {
+                let start = (y * width as usize + page_x) * bytes_per_pixel;
+                image[start..start + row_size].copy_from_slice(&row[..row_size]);
+            }
+            page_x += size as usize;
+        }
+        (image, [width, height])
+    }
+
+    /// Reads level 0 of all pages back from the GPU and encodes them as one PNG, grayscale or
+    /// RGBA with subpixel rendering, with the pages next to each other.
+    /// Returns an empty vector if there are no pages yet.
+    #[cfg(feature = "export")]
+    pub fn export_png(&self, device: &Device, queue: &Queue) -> Vec<u8> {
+        let (image, [width, height]) = self.read_pixels_cpu(device, queue);
+        if image.is_empty() {
+            return Vec::new();
+        }
+        let mut png = Vec::new();
+        let mut encoder = png::Encoder::new(&mut png, width, height);
+        encoder.set_color(match self.resources.bytes_per_pixel() {
+            4 => png::ColorType::Rgba,
+            _ => png::ColorType::Grayscale,
+        });
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder
+            .write_header()
+            .and_then(|mut writer| writer.write_image_data(&image))
+            .expect("Failed to encode the atlas as PNG");
+        png
+    }
+
+    /// Writes all prepared glyphs to `writer`, least recently used first.
+    /// `font_hash` maps a font id to the hash of its raw file content,
+    /// glyphs of fonts it returns `None` for are not written.
+    pub fn write_cache(
+        &self,
+        writer: &mut impl Write,
+        font_hash: impl Fn(F) -> Option<u64>,
+    ) -> Result<(), EasyTextError> {
+        let entries: Vec<_> = self
+            .pages
+            .values()
+            .flat_map(|page| page.allocated.iter().rev())
+            .filter_map(|(&(font_id, size, glyph_index), glyph)| {
+                font_hash(font_id).map(|hash| (hash, size, glyph_index, glyph))
+            })
+            .collect();
+        writer.write_all(CACHE_MAGIC)?;
+        writer.write_all(&CACHE_VERSION.to_le_bytes())?;
+        let atlas_size = self.pages.values().map(|page| page.size).max();
+        writer.write_all(&atlas_size.unwrap_or(self.initial_size).to_le_bytes())?;
+        writer.write_all(&(entries.len() as u32).to_le_bytes())?;
+        for (hash, size, glyph_index, glyph) in entries {
+            let metrics = &glyph.metrics;
+            writer.write_all(&hash.to_le_bytes())?;
+            writer.write_all(&size.to_le_bytes())?;
+            writer.write_all(&glyph_index.to_le_bytes())?;
+            writer.write_all(&metrics.xmin.to_le_bytes())?;
+            writer.write_all(&metrics.ymin.to_le_bytes())?;
+            writer.write_all(&(metrics.width as u32).to_le_bytes())?;
+            writer.write_all(&(metrics.height as u32).to_le_bytes())?;
+            writer.write_all(&metrics.advance_width.to_le_bytes())?;
+            writer.write_all(&metrics.advance_height.to_le_bytes())?;
+            writer.write_all(&metrics.bounds.xmin.to_le_bytes())?;
+            writer.write_all(&metrics.bounds.ymin.to_le_bytes())?;
+            writer.write_all(&metrics.bounds.width.to_le_bytes())?;
+            writer.write_all(&metrics.bounds.height.to_le_bytes())?;
+            writer.write_all(&(glyph.bitmap.len() as u32).to_le_bytes())?;
+            writer.write_all(&glyph.bitmap)?;
+        }
+        Ok(())
+    }
+
+    /// Reads glyphs written by `write_cache` and uploads them to the atlas.
+    /// `font_id` maps a font hash back to a loaded font, glyphs of unknown fonts are skipped.
+    pub fn read_cache(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        reader: &mut impl Read,
+        font_id: impl Fn(u64) -> Option<F>,
+    ) -> Result<(), EasyTextError> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic).map_err(cache_error)?;
+        if &magic != CACHE_MAGIC || read_u32(reader)? != CACHE_VERSION {
+            return Err(EasyTextError::InvalidAtlasCache);
+        }
+        let atlas_size = read_u32(reader)?.min(self.resources.max_size);
+        let count = read_u32(reader)?;
+        for _ in 0..count {
+            let hash = u64::from_le_bytes(read_bytes(reader)?);
+            let size = u16::from_le_bytes(read_bytes(reader)?);
+            let glyph_index = u16::from_le_bytes(read_bytes(reader)?);
+            let metrics = Metrics {
+                xmin: i32::from_le_bytes(read_bytes(reader)?),
+                ymin: i32::from_le_bytes(read_bytes(reader)?),
+                width: read_u32(reader)? as usize,
+                height: read_u32(reader)? as usize,
+                advance_width: f32::from_le_bytes(read_bytes(reader)?),
+                advance_height: f32::from_le_bytes(read_bytes(reader)?),
+                bounds: OutlineBounds {
+                    xmin: f32::from_le_bytes(read_bytes(reader)?),
+                    ymin: f32::from_le_bytes(read_bytes(reader)?),
+                    width: f32::from_le_bytes(read_bytes(reader)?),
+                    height: f32::from_le_bytes(read_bytes(reader)?),
+                },
+            };
+            // The length is checked before allocating the bitmap, so a corrupt cache can't make
+            // it allocate more than a glyph filling the largest page. Also rejects caches written
+            // with subpixel rendering on or off when it now isn't
+            let max_size = self.resources.max_size as usize;
+            let bytes_per_pixel = self.resources.bytes_per_pixel() as usize;
+            let bitmap_len = read_u32(reader)? as usize;
+            if metrics.width > max_size
+                || metrics.height > max_size
+                || bitmap_len != metrics.width * metrics.height * bytes_per_pixel
+            {
+                return Err(EasyTextError::InvalidAtlasCache);
+            }
+            let mut bitmap = vec![0; bitmap_len];
+            reader.read_exact(&mut bitmap).map_err(cache_error)?;
+            let Some(font_id) = font_id(hash) else {
+                continue;
+            };
+            let key = self.page_key(font_id);
+            self.page_mut(device, font_id);
+            let page = self.pages.get_mut(&key).unwrap();
+            if page.allocated.contains(&(font_id, size, glyph_index)) {
+                continue;
+            }
+            while page.size < atlas_size {
+                page.grow(device, queue, &self.resources).unwrap();
+            }
+            self.insert(device, queue, font_id, size, glyph_index, metrics, bitmap);
+        }
+        Ok(())
+    }
+}
+
+/// Whether glyphs are rendered with subpixel antialiasing, which needs the `subpixel` feature
+/// and dual source blending. Distance fields take precedence with the `sdf` feature.
+pub(crate) fn subpixel_enabled(device: &Device) -> bool {
+    cfg!(feature = "subpixel")
+        && !cfg!(feature = "sdf")
+        && device
+            .features()
+            .contains(wgpu::Features::DUAL_SOURCE_BLENDING)
+}
+
+fn create_mipmap_pipeline(
+    device: &Device,
+    layout: &BindGroupLayout,
+    format: TextureFormat,
+) -> RenderPipeline {
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some("EasyText Atlas Mipmap Shader"),
+        source: wgpu::ShaderSource::Wgsl(include_str!("./shaders/mipmap.wgsl").into()),
+    });
+    let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some("EasyText Atlas Mipmap Pipeline Layout"),
+        bind_group_layouts: &[layout],
+        push_constant_ranges: &[],
+    });
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some("EasyText Atlas Mipmap Pipeline"),
+        layout: Some(&pipeline_layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: "vs_main",
+            buffers: &[],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: "fs_main",
+            targets: &[Some(wgpu::ColorTargetState {
+                format,
+                blend: None,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: wgpu::PipelineCompilationOptions::default(),
+        }),
+        primitive: wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleStrip,
+            strip_index_format: None,
+            front_face: wgpu::FrontFace::Cw,
+            cull_mode: None,
+            polygon_mode: wgpu::PolygonMode::Fill,
+            unclipped_depth: false,
+            conservative: false,
+        },
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState {
+            count: 1,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+// Coverage values are raised to the power of 1 / GAMMA_CORRECTION with `gamma_correct`.
+// Partly covered pixels become more opaque, so the antialiased edges of the glyphs aren't
+// blended too light on sRGB displays
+const GAMMA_CORRECTION: f32 = 1.8;
+
+// Gamma corrected value of every coverage value, see `GAMMA_CORRECTION`
+fn gamma_table() -> [u8; 256] {
+    std::array::from_fn(|coverage| {
+        let corrected = (coverage as f32 / 255.0).powf(1.0 / GAMMA_CORRECTION);
+        (corrected * 255.0).round() as u8
+    })
+}
+
+// Writes `bitmap` surrounded by the glyph padding of empty pixels, which also clears what
+// evicted glyphs left behind in the padding
+fn write_glyph(
+    queue: &Queue,
+    texture: &Texture,
+    allocation: &Allocation,
+    metrics: &Metrics,
+    bitmap: &[u8],
+    resources: &PageResources,
+) {
+    let padding = resources.glyph_padding;
+    let bytes_per_pixel = resources.bytes_per_pixel() as usize;
+    let [width, height] = [
+        metrics.width as u32 + 2 * padding,
+        metrics.height as u32 + 2 * padding,
+    ];
+    let row_size = metrics.width * bytes_per_pixel;
+    let mut padded = vec![0; width as usize * height as usize * bytes_per_pixel];
+    for (y, row) in bitmap.chunks(row_size).enumerate() {
+        let start = ((y + padding as usize) * width as usize + padding as usize) * bytes_per_pixel;
+        let target = &mut padded[start..start + row_size];
+        match &resources.gamma_table {
+            Some(table) => {
+                for (target, &coverage) in target.iter_mut().zip(row) {
+                    *target = table[coverage as usize];
+                }
+            }
+            None => target.copy_from_slice(row),
+        }
+    }
+    queue.write_texture(
+        wgpu::ImageCopyTexture {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d {
+                x: allocation.rectangle.min.x as u32,
+                y: allocation.rectangle.min.y as u32,
+                z: 0,
+            },
+            aspect: wgpu::TextureAspect::All,
+        },
+        &padded,
+        wgpu::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: Some(width * bytes_per_pixel as u32),
+            rows_per_image: None,
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+}
+
+fn cache_error(err: std::io::Error) -> EasyTextError {
+    match err.kind() {
+        std::io::ErrorKind::UnexpectedEof => EasyTextError::InvalidAtlasCache,
+        _ => EasyTextError::Io(err),
+    }
+}
+
+fn read_bytes<const N: usize>(reader: &mut impl Read) -> Result<[u8; N], EasyTextError> {
+    let mut bytes = [0; N];
+    reader.read_exact(&mut bytes).map_err(cache_error)?;
+    Ok(bytes)
+}
+
+fn read_u32(reader: &mut impl Read) -> Result<u32, EasyTextError> {
+    Ok(u32::from_le_bytes(read_bytes(reader)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gamma_table_darkens_partial_coverage() {
+        let table = gamma_table();
+        assert_eq!(table[0], 0);
+        assert_eq!(table[255], 255);
+        assert!((0..256).all(|coverage| table[coverage] as usize >= coverage));
+        assert!([32, 128, 224]
+            .into_iter()
+            .all(|coverage| table[coverage] as usize > coverage));
+        assert!(table.windows(2).all(|pair| pair[0] <= pair[1]));
     }
 }
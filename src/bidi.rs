@@ -0,0 +1,62 @@
+use unicode_bidi::BidiInfo;
+
+use crate::LogicalGlyph;
+
+/// Text reordered from logical (memory) order into the visual order it is laid out in.
+pub(crate) struct BidiText {
+    pub visual: String,
+    // Logical byte offset and direction for every byte of `visual`
+    logical: Vec<LogicalGlyph>,
+}
+
+impl BidiText {
+    /// Reorders every paragraph of `text` on its own. The paragraph direction is taken from
+    /// the first strong character. Wrapped lines are not reordered separately, so long mixed
+    /// direction paragraphs should be broken up with hard line breaks.
+    pub fn new(text: &str) -> Self {
+        let info = BidiInfo::new(text, None);
+        let mut visual = String::with_capacity(text.len());
+        let mut logical = Vec::with_capacity(text.len());
+        let mut push = |c: char, offset: usize, rtl: bool| {
+            visual.push(c);
+            for _ in 0..c.len_utf8() {
+                logical.push(LogicalGlyph { offset, rtl });
+            }
+        };
+        for paragraph in &info.paragraphs {
+            // The line break is kept at the end instead of being reordered with the text
+            let range = paragraph.range.clone();
+            let break_len = match &text[range.clone()] {
+                line if line.ends_with("\r\n") => 2,
+                line if line.ends_with('\n') => 1,
+                _ => 0,
+            };
+            let line = range.start..range.end - break_len;
+            if !line.is_empty() {
+                let (levels, runs) = info.visual_runs(paragraph, line.clone());
+                for run in runs {
+                    let rtl = levels[run.start].is_rtl();
+                    let chars = text[run.clone()].char_indices();
+                    if rtl {
+                        for (i, c) in chars.rev() {
+                            push(c, run.start + i, true);
+                        }
+                    } else {
+                        for (i, c) in chars {
+                            push(c, run.start + i, false);
+                        }
+                    }
+                }
+            }
+            for (i, c) in text[line.end..range.end].char_indices() {
+                push(c, line.end + i, false);
+            }
+        }
+        Self { visual, logical }
+    }
+
+    /// Logical position of the glyph at `visual_offset` in the laid out text.
+    pub fn logical_glyph(&self, visual_offset: usize) -> LogicalGlyph {
+        self.logical[visual_offset]
+    }
+}
@@ -0,0 +1,255 @@
+use std::{
+    future::Future,
+    hash::Hash,
+    marker::PhantomData,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use wgpu::{DepthStencilState, Device, TextureFormat};
+
+use crate::{
+    atlas::{AtlasPacker, AtlasSettings},
+    EasyText, EasyTextError, GlyphVertex, VertexFormat,
+};
+
+/// How the output of the glyph and rect shaders is blended with the render target.
+/// Set for all text areas with `EasyTextBuilder::blend_mode` or per text area with `TextArea::blend_mode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BlendMode {
+    /// Colors are not premultiplied with their alpha, `wgpu::BlendState::ALPHA_BLENDING`
+    #[default]
+    StraightAlpha,
+    /// The shaders output `rgb * a`, blended with `wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING`.
+    /// For compositing over premultiplied alpha framebuffers.
+    PremultipliedAlpha,
+    /// The color (multiplied with its alpha) is added to the render target,
+    /// for glowing text on dark backgrounds
+    Additive,
+}
+
+/// Configures and creates an `EasyText` instance.
+/// Use `EasyText::new` if the default configuration is sufficient.
+pub struct EasyTextBuilder<F: Eq + Hash + Copy, V: VertexFormat = GlyphVertex> {
+    pub(crate) window_size: [u32; 2],
+    pub(crate) surface_format: TextureFormat,
+    pub(crate) atlas: AtlasSettings,
+    pub(crate) sample_count: u32,
+    pub(crate) depth_stencil: Option<DepthStencilState>,
+    #[cfg(feature = "sdf")]
+    pub(crate) sdf_settings: crate::SdfSettings,
+    pub(crate) custom_shader: Option<String>,
+    pub(crate) blend_mode: BlendMode,
+    pub(crate) use_index_buffer: bool,
+    _font: PhantomData<F>,
+    _vertex: PhantomData<V>,
+}
+
+impl<F: Eq + Hash + Copy> EasyTextBuilder<F> {
+    pub fn new(window_width: u32, window_height: u32, surface_format: TextureFormat) -> Self {
+        Self {
+            window_size: [window_width, window_height],
+            surface_format,
+            atlas: AtlasSettings::default(),
+            sample_count: 1,
+            depth_stencil: None,
+            #[cfg(feature = "sdf")]
+            sdf_settings: crate::SdfSettings::default(),
+            custom_shader: None,
+            blend_mode: BlendMode::default(),
+            use_index_buffer: false,
+            _font: PhantomData,
+            _vertex: PhantomData,
+        }
+    }
+}
+
+impl<F: Eq + Hash + Copy, V: VertexFormat> EasyTextBuilder<F, V> {
+    /// Sets the size of the window in physical pixels, replacing the one given to `new`.
+    pub fn window_size(mut self, window_width: u32, window_height: u32) -> Self {
+        self.window_size = [window_width, window_height];
+        self
+    }
+
+    /// Sets the format of the render target, replacing the one given to `new`.
+    pub fn surface_format(mut self, surface_format: TextureFormat) -> Self {
+        self.surface_format = surface_format;
+        self
+    }
+
+    /// Sets the number of samples per pixel of the render target (default 1).
+    /// Has to match the sample count of the color attachment of the render pass
+    /// passed to `EasyText::render`, otherwise wgpu will panic when rendering into an MSAA framebuffer.
+    pub fn sample_count(mut self, sample_count: u32) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+
+    /// Enables depth testing against the depth attachment of the render pass (default None).
+    /// The depth of the glyphs is taken from `TextArea::z_depth`, debug overlays are drawn at depth 0.
+    /// The caller is responsible for attaching a depth texture of a matching format to the render pass.
+    pub fn depth_stencil(mut self, depth_stencil: Option<DepthStencilState>) -> Self {
+        self.depth_stencil = depth_stencil;
+        self
+    }
+
+    /// Sets the initial width and height of the glyph atlas texture (default 512).
+    /// The value is clamped to `[64, max_texture_dimension_2d]` and rounded up to the next power of two.
+    /// The atlas grows on its own when it runs out of space, a larger initial size avoids early grows
+    /// but wastes VRAM if most of the space is never used.
+    pub fn initial_atlas_size(mut self, size: u32) -> Self {
+        self.atlas.initial_size = size;
+        self
+    }
+
+    /// Sets the size in pixels the atlas stops growing at (default 8192), glyphs are evicted
+    /// instead once it is full. Clamped to `max_texture_dimension_2d` of the device and never
+    /// smaller than the initial size.
+    pub fn max_atlas_size(mut self, size: u32) -> Self {
+        self.atlas.max_size = size;
+        self
+    }
+
+    /// Gives every font its own atlas texture (default false).
+    /// This way glyphs of a large font can't evict the glyphs of other fonts,
+    /// at the cost of one texture and one bind group switch per font.
+    pub fn per_font_atlas(mut self, per_font_atlas: bool) -> Self {
+        self.atlas.per_font_atlas = per_font_atlas;
+        self
+    }
+
+    /// Generates mipmaps for the atlas textures and samples them with linear filtering (default false).
+    /// Reduces shimmering of text that is scaled down, at the cost of a render pass per mip level
+    /// whenever glyphs are added to the atlas.
+    pub fn use_mipmaps(mut self, use_mipmaps: bool) -> Self {
+        self.atlas.use_mipmaps = use_mipmaps;
+        self
+    }
+
+    /// Enables anisotropic filtering for the atlas sampler, which reduces aliasing of text
+    /// that is viewed at an oblique angle. `clamp` is the maximum anisotropy, clamped to `[1, 16]`.
+    /// Implies linear filtering. Devices without anisotropic filtering support
+    /// fall back to plain linear filtering.
+    pub fn with_anisotropic_filtering(mut self, clamp: u16) -> Self {
+        self.atlas.anisotropy_clamp = Some(clamp);
+        self
+    }
+
+    /// Sets the number of empty pixels around every glyph in the atlas (default 1).
+    /// 1 is enough to keep linear filtering from bleeding neighbouring glyphs into each other,
+    /// mipmaps need more the more levels are sampled.
+    pub fn glyph_padding(mut self, glyph_padding: u32) -> Self {
+        self.atlas.glyph_padding = glyph_padding;
+        self
+    }
+
+    /// Gamma corrects the glyph coverage, so text on sRGB displays doesn't appear too light
+    /// (default false). Every coverage value is raised to the power of 1 / 1.8, which makes the
+    /// antialiased edges more opaque and the text darker and bolder, while fully covered and
+    /// empty pixels stay the same. Happens on the CPU before the upload, so the atlas stays
+    /// `R8Unorm`.
+    /// Has no effect with the `sdf` feature, where it would move the outline of the glyphs.
+    pub fn gamma_correct(mut self, gamma_correct: bool) -> Self {
+        self.atlas.gamma_correct = gamma_correct;
+        self
+    }
+
+    /// Rounds font sizes to the nearest multiple of `size_quantization` before rasterizing
+    /// glyphs (default 1.0). With 4.0, all sizes from 14 to just below 18 share the glyphs
+    /// rasterized at 16px, which are scaled to the exact size. Reduces the atlas size of
+    /// applications with many fractional or animated font sizes. Has no effect with the `sdf` feature.
+    pub fn size_quantization(mut self, size_quantization: f32) -> Self {
+        self.atlas.size_quantization = size_quantization;
+        self
+    }
+
+    /// Sets how glyphs are packed into the atlas (default `AtlasPacker::Bucketed`).
+    /// See the variants of `AtlasPacker` for the trade-offs.
+    pub fn with_atlas_packer(mut self, packer: AtlasPacker) -> Self {
+        self.atlas.packer = packer;
+        self
+    }
+
+    /// Sets how the signed distance fields of the glyphs are generated.
+    #[cfg(feature = "sdf")]
+    pub fn sdf_settings(mut self, sdf_settings: crate::SdfSettings) -> Self {
+        self.sdf_settings = sdf_settings;
+        self
+    }
+
+    /// Uses `V2` instead of `GlyphVertex` for the vertices of the glyphs.
+    pub fn vertex_format<V2: VertexFormat>(self) -> EasyTextBuilder<F, V2> {
+        EasyTextBuilder {
+            window_size: self.window_size,
+            surface_format: self.surface_format,
+            atlas: self.atlas,
+            sample_count: self.sample_count,
+            depth_stencil: self.depth_stencil,
+            #[cfg(feature = "sdf")]
+            sdf_settings: self.sdf_settings,
+            custom_shader: self.custom_shader,
+            blend_mode: self.blend_mode,
+            use_index_buffer: self.use_index_buffer,
+            _font: PhantomData,
+            _vertex: PhantomData,
+        }
+    }
+
+    /// Draws glyphs with an index buffer of `u16` indices shared by all text areas (default false).
+    /// Every glyph, shadow and outline then takes 4 instead of 6 vertices, which saves VRAM
+    /// and vertex uploads as soon as a few glyphs are drawn.
+    pub fn use_index_buffer(mut self, use_index_buffer: bool) -> Self {
+        self.use_index_buffer = use_index_buffer;
+        self
+    }
+
+    /// Sets how text is blended with the render target (default `BlendMode::StraightAlpha`).
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Replaces the built-in glyph shader with the WGSL source `wgsl_source` (default None).
+    /// The shader has to provide:
+    /// - the vertex entry points `vs_main` and `vs_instanced`, reading the position (`vec3<f32>`),
+    ///   texture coordinate (`vec2<f32>`) and color (`vec4<f32>`) from locations 0 to 2,
+    ///   `vs_instanced` additionally reads the offset of the instance (`vec2<f32>`) from location 3
+    /// - the fragment entry point `fs_main` (`fs_sdf` with the `sdf` feature, `fs_subpixel` with
+    ///   a dual source output if subpixel rendering is active, see `src/shaders/subpixel.wgsl`)
+    /// - the bind groups of the atlas (0: texture, 1: sampler), the window size
+    ///   (group 1, a `vec2<u32>` uniform) and the transform of the text area (group 2, a `mat4x4<f32>` uniform,
+    ///   followed by the rect and radius of `TextArea::corner_radius`, see `src/shaders/bindings.wgsl`)
+    ///
+    /// The shader is compiled as it is. With the `push_constants` feature and a device requested with
+    /// `wgpu::Features::PUSH_CONSTANTS`, the window size is a push constant
+    /// (`var<push_constant> meta_info: MetaInfo;`) instead of a uniform and the transform of the text area
+    /// is in group 1 instead of 2, see `src/shaders/bindings_push_constants.wgsl`.
+    ///
+    /// With `BlendMode::PremultipliedAlpha`, the shader also has to declare `override premultiplied_alpha: bool`.
+    ///
+    /// See `src/shaders/shader.wgsl` for the built-in shader, which is compiled after the bindings.
+    /// Use `try_build` to get an error instead of a panic if the shader is invalid.
+    pub fn custom_shader(mut self, wgsl_source: impl Into<String>) -> Self {
+        self.custom_shader = Some(wgsl_source.into());
+        self
+    }
+
+    pub fn build(self, device: &Device) -> EasyText<F, V> {
+        EasyText::from_builder(self, device)
+    }
+
+    /// Like `build`, but returns `EasyTextError::InvalidShader` instead of panicking
+    /// if the pipelines can't be created, e.g. because of an invalid custom shader.
+    /// Only native backends are checked, on the web errors are reported asynchronously.
+    pub fn try_build(self, device: &Device) -> Result<EasyText<F, V>, EasyTextError> {
+        device.push_error_scope(wgpu::ErrorFilter::Validation);
+        let easy_text = EasyText::from_builder(self, device);
+        // Native backends report errors right away, so the future is ready on the first poll
+        let error = pin!(device.pop_error_scope());
+        match error.poll(&mut Context::from_waker(Waker::noop())) {
+            Poll::Ready(Some(err)) => Err(EasyTextError::InvalidShader(err)),
+            _ => Ok(easy_text),
+        }
+    }
+}
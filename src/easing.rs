@@ -0,0 +1,65 @@
+//! Animation curves, mapping the linear progress `t` ([0, 1]) of an animation to an eased one.
+//! Used by `EasyText::animate_color`, but usable for any animation.
+
+use std::f32::consts::TAU;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Easing {
+    #[default]
+    Linear,
+    // Starts slow
+    EaseIn,
+    // Ends slow
+    EaseOut,
+    // Starts and ends slow
+    EaseInOut,
+}
+
+/// Applies `easing` to the progress `t`.
+pub fn apply(easing: Easing, t: f32) -> f32 {
+    match easing {
+        Easing::Linear => linear(t),
+        Easing::EaseIn => ease_in_quad(t),
+        Easing::EaseOut => ease_out_quad(t),
+        Easing::EaseInOut => ease_in_out_cubic(t),
+    }
+}
+
+pub fn linear(t: f32) -> f32 {
+    t
+}
+
+pub fn ease_in_quad(t: f32) -> f32 {
+    t * t
+}
+
+pub fn ease_out_quad(t: f32) -> f32 {
+    1.0 - (1.0 - t) * (1.0 - t)
+}
+
+pub fn ease_in_out_cubic(t: f32) -> f32 {
+    match t < 0.5 {
+        true => 4.0 * t * t * t,
+        false => 1.0 - (-2.0 * t + 2.0).powi(3) / 2.0,
+    }
+}
+
+/// Moves backwards before starting, `overshoot` controls how far (1.70158 is a common value).
+pub fn ease_in_back(t: f32, overshoot: f32) -> f32 {
+    t * t * ((overshoot + 1.0) * t - overshoot)
+}
+
+/// Overshoots the end and oscillates around it. `amplitude` (at least 1) is the size of the
+/// first overshoot, `period` the length of one oscillation in units of `t`.
+pub fn ease_out_elastic(t: f32, amplitude: f32, period: f32) -> f32 {
+    if t <= 0.0 || t >= 1.0 {
+        return t.clamp(0.0, 1.0);
+    }
+    let amplitude = amplitude.max(1.0);
+    let shift = period / TAU * (1.0 / amplitude).asin();
+    amplitude * 2f32.powf(-10.0 * t) * ((t - shift) * TAU / period).sin() + 1.0
+}
@@ -0,0 +1,56 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum EasyTextError {
+    Io(std::io::Error),
+    // The atlas cache file is truncated or was not written by easytext
+    InvalidAtlasCache,
+    // fontdue couldn't parse the font file, see `EasyText::add_font_from_path` and
+    // `EasyText::add_font_family`
+    InvalidFontData(&'static str),
+    // A text area still uses the font, see `EasyText::remove_font`
+    FontInUse,
+    #[cfg(feature = "regex")]
+    InvalidPattern(regex::Error),
+    // Creating the pipelines with the custom shader failed, see `EasyTextBuilder::try_build`
+    InvalidShader(wgpu::Error),
+}
+
+impl fmt::Display for EasyTextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EasyTextError::Io(err) => write!(f, "I/O error: {}", err),
+            EasyTextError::InvalidAtlasCache => write!(f, "invalid atlas cache file"),
+            EasyTextError::InvalidFontData(err) => write!(f, "invalid font data: {}", err),
+            EasyTextError::FontInUse => write!(f, "font is still used by a text area"),
+            #[cfg(feature = "regex")]
+            EasyTextError::InvalidPattern(err) => write!(f, "invalid pattern: {}", err),
+            EasyTextError::InvalidShader(err) => write!(f, "invalid shader: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for EasyTextError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EasyTextError::Io(err) => Some(err),
+            #[cfg(feature = "regex")]
+            EasyTextError::InvalidPattern(err) => Some(err),
+            EasyTextError::InvalidShader(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for EasyTextError {
+    fn from(err: std::io::Error) -> Self {
+        EasyTextError::Io(err)
+    }
+}
+
+#[cfg(feature = "regex")]
+impl From<regex::Error> for EasyTextError {
+    fn from(err: regex::Error) -> Self {
+        EasyTextError::InvalidPattern(err)
+    }
+}
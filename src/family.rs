@@ -0,0 +1,91 @@
+use std::hash::Hash;
+
+use ahash::HashMap;
+use fontdue::Font;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+use crate::area::TextArea;
+
+/// Weight of a font face, from 100 (thin) over 400 (regular) and 700 (bold) to 900 (black).
+pub type FontWeight = u16;
+
+pub(crate) const DEFAULT_FONT_WEIGHT: FontWeight = 400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum FontStyle {
+    #[default]
+    Normal,
+    Italic,
+    Oblique,
+}
+
+impl FontStyle {
+    // Styles to fall back to if a family has no face of this style, best first
+    fn fallbacks(self) -> [FontStyle; 3] {
+        match self {
+            FontStyle::Normal => [FontStyle::Normal, FontStyle::Oblique, FontStyle::Italic],
+            FontStyle::Italic => [FontStyle::Italic, FontStyle::Oblique, FontStyle::Normal],
+            FontStyle::Oblique => [FontStyle::Oblique, FontStyle::Italic, FontStyle::Normal],
+        }
+    }
+}
+
+/// The faces added for one font id, a single regular face for fonts added with `add_font`.
+pub(crate) struct FontFamily {
+    faces: Vec<(FontWeight, FontStyle, Font)>,
+}
+
+impl FontFamily {
+    pub fn new(faces: Vec<(FontWeight, FontStyle, Font)>) -> Self {
+        Self { faces }
+    }
+
+    pub fn faces(&self) -> impl Iterator<Item = (u16, &Font)> {
+        self.faces
+            .iter()
+            .enumerate()
+            .map(|(index, (_, _, font))| (index as u16, font))
+    }
+
+    pub fn face(&self, index: u16) -> Option<&Font> {
+        self.faces.get(index as usize).map(|(_, _, font)| font)
+    }
+
+    /// Index and font of the face that matches `weight` and `style` best. Faces of the closest
+    /// style win over closer weights, ties between weights go to the bolder face for weights
+    /// of 400 and above and to the lighter one below, roughly like CSS font matching.
+    pub fn resolve(&self, weight: FontWeight, style: FontStyle) -> Option<(u16, &Font)> {
+        style.fallbacks().into_iter().find_map(|style| {
+            self.faces
+                .iter()
+                .enumerate()
+                .filter(|(_, face)| face.1 == style)
+                .min_by_key(|(_, face)| {
+                    let prefers_face = match weight >= DEFAULT_FONT_WEIGHT {
+                        true => face.0 >= weight,
+                        false => face.0 <= weight,
+                    };
+                    (face.0.abs_diff(weight), !prefers_face)
+                })
+                .map(|(index, face)| (index as u16, &face.2))
+        })
+    }
+
+    /// The face used for queries that don't specify a weight and style.
+    pub fn regular(&self) -> Option<&Font> {
+        self.resolve(DEFAULT_FONT_WEIGHT, FontStyle::Normal)
+            .map(|(_, font)| font)
+    }
+}
+
+// Index and font of the face of the font family of `area` it is drawn with
+pub(crate) fn resolve_font<'a, F: Eq + Hash + Copy>(
+    fonts: &'a HashMap<F, FontFamily>,
+    area: &TextArea<F>,
+) -> Option<(u16, &'a Font)> {
+    fonts
+        .get(&area.font)?
+        .resolve(area.font_weight, area.font_style)
+}
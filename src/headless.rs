@@ -0,0 +1,200 @@
+use std::{hash::Hash, path::Path};
+
+use image::{Rgba, RgbaImage};
+use pollster::FutureExt;
+use wgpu::{Device, Queue, TextureFormat};
+
+use crate::{EasyText, VertexFormat};
+
+// Format of the texture `render_to_rgba_cpu` renders to
+const HEADLESS_FORMAT: TextureFormat = TextureFormat::Rgba8Unorm;
+
+// The device an `EasyText` created with `new_headless` renders with
+pub(crate) struct Headless {
+    pub device: Device,
+    pub queue: Queue,
+}
+
+impl<F: Eq + Hash + Copy> EasyText<F> {
+    /// Creates an `EasyText` that renders with its own device on a software adapter, for tests
+    /// on machines without a GPU. Render it with `render_to_rgba_cpu`.
+    ///
+    /// Panics if there is no software adapter (e.g. llvmpipe or WARP).
+    pub fn new_headless(window_width: u32, window_height: u32) -> Self {
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            // GL is included for llvmpipe, the only software adapter on many Linux machines
+            backends: wgpu::Backends::VULKAN
+                | wgpu::Backends::METAL
+                | wgpu::Backends::DX12
+                | wgpu::Backends::GL,
+            ..Default::default()
+        });
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                force_fallback_adapter: true,
+                ..Default::default()
+            })
+            .block_on()
+            .expect("No software adapter found");
+        // Push constants and subpixel rendering are used if their feature is enabled
+        // and the adapter supports them
+        let mut features = wgpu::Features::empty();
+        if cfg!(feature = "push_constants") {
+            features |= adapter.features() & wgpu::Features::PUSH_CONSTANTS;
+        }
+        if cfg!(feature = "subpixel") {
+            features |= adapter.features() & wgpu::Features::DUAL_SOURCE_BLENDING;
+        }
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    required_features: features,
+                    required_limits: wgpu::Limits {
+                        max_push_constant_size: adapter.limits().max_push_constant_size,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                None,
+            )
+            .block_on()
+            .expect("Failed to create a device on the software adapter");
+        let mut easy_text = Self::new(window_width, window_height, &device, HEADLESS_FORMAT);
+        easy_text.headless = Some(Headless { device, queue });
+        easy_text
+    }
+}
+
+impl<F: Eq + Hash + Copy, V: VertexFormat> EasyText<F, V> {
+    /// Renders all text areas onto a transparent image of the window size and reads it back.
+    ///
+    /// Panics if this `EasyText` was not created with `new_headless`.
+    pub fn render_to_rgba_cpu(&mut self) -> RgbaImage {
+        let Headless { device, queue } = self
+            .headless
+            .take()
+            .expect("render_to_rgba_cpu needs an EasyText created with new_headless");
+        let [width, height] = self.window_size;
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("EasyText Headless Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HEADLESS_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bytes_per_row = (width * 4).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("EasyText Headless Readback Buffer"),
+            size: bytes_per_row as u64 * height as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("EasyText Headless Encoder"),
+        });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("EasyText Headless Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            self.render(&device, &queue, &mut render_pass);
+        }
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: None,
+                },
+            },
+            size,
+        );
+        queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |result| {
+            result.expect("Failed to map the headless readback buffer")
+        });
+        device.poll(wgpu::Maintain::Wait);
+        let mut pixels = Vec::with_capacity((width * height * 4) as usize);
+        for row in slice.get_mapped_range().chunks(bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..(width * 4) as usize]);
+        }
+        buffer.unmap();
+        self.headless = Some(Headless { device, queue });
+        RgbaImage::from_raw(width, height, pixels).unwrap()
+    }
+
+    /// Renders with `render_to_rgba_cpu` and compares the result with the PNG at `snapshot_path`.
+    /// `tolerance` is the largest allowed difference of a color channel, from 0.0 to 1.0.
+    /// Saves the rendered image as the snapshot if there is none yet.
+    ///
+    /// Panics if the images differ, after saving an image with the differing pixels in red
+    /// next to the snapshot (`<name>.diff.png`).
+    pub fn assert_snapshot(&mut self, snapshot_path: &Path, tolerance: f32) {
+        let actual = self.render_to_rgba_cpu();
+        if !snapshot_path.exists() {
+            actual
+                .save(snapshot_path)
+                .expect("Failed to save the snapshot");
+            return;
+        }
+        let expected = image::open(snapshot_path)
+            .expect("Failed to read the snapshot")
+            .into_rgba8();
+        assert_eq!(
+            expected.dimensions(),
+            actual.dimensions(),
+            "Snapshot {} has a different size",
+            snapshot_path.display()
+        );
+        let max_difference = (tolerance.clamp(0.0, 1.0) * 255.0).round() as u8;
+        let mut differing = 0;
+        let diff = RgbaImage::from_fn(actual.width(), actual.height(), |x, y| {
+            let [a, e] = [actual.get_pixel(x, y), expected.get_pixel(x, y)];
+            if a.0
+                .iter()
+                .zip(e.0)
+                .any(|(a, e)| a.abs_diff(e) > max_difference)
+            {
+                differing += 1;
+                Rgba([255, 0, 0, 255])
+            } else {
+                // Faded so the differences stand out
+                Rgba([a[0], a[1], a[2], a[3] / 4])
+            }
+        });
+        if differing > 0 {
+            let diff_path = snapshot_path.with_extension("diff.png");
+            diff.save(&diff_path)
+                .expect("Failed to save the diff image");
+            panic!(
+                "{differing} pixels differ from snapshot {}, see {}",
+                snapshot_path.display(),
+                diff_path.display()
+            );
+        }
+    }
+}
@@ -0,0 +1,101 @@
+use std::hash::Hash;
+
+use fontdue::{layout::Layout, Font};
+
+use crate::{area::TextArea, layout_text};
+
+/// Text with hyphens and line breaks inserted into words that would otherwise be
+/// wrapped onto the next line as a whole.
+pub(crate) struct HyphenatedText {
+    pub text: String,
+    // Byte offset into the original text for every byte of `text`
+    logical: Vec<usize>,
+}
+
+impl HyphenatedText {
+    /// Lays out the text of `area` in `layout` at `size` with `max_width` and, line by line, asks
+    /// `hyphenation` for the break points of the first word of every wrapped line.
+    /// The longest start of the word that still fits on the previous line is moved there,
+    /// followed by a hyphen. Returns `None` if no word was hyphenated.
+    pub fn new<F: Eq + Hash + Copy>(
+        layout: &mut Layout,
+        font: &Font,
+        area: &TextArea<F>,
+        size: f32,
+        max_width: f32,
+        hyphenation: &dyn Fn(&str) -> Vec<usize>,
+    ) -> Option<Self> {
+        let advance = |text: &str| -> f32 {
+            text.chars()
+                .map(|c| font.metrics(c, size).advance_width)
+                .sum()
+        };
+        let hyphen_width = advance("-");
+        let mut text = area.text.clone();
+        let mut logical: Vec<usize> = (0..text.len()).collect();
+        let mut next_line = 1;
+        loop {
+            layout_text(
+                layout,
+                font,
+                area,
+                size,
+                Some(&text),
+                0.0,
+                0.0,
+                Some(max_width),
+                None,
+            );
+            let (Some(lines), glyphs) = (layout.lines(), layout.glyphs()) else {
+                break;
+            };
+            let pen_x = |index: usize| {
+                let glyph = &glyphs[index];
+                glyph.x - font.metrics_indexed(glyph.key.glyph_index, size).xmin as f32
+            };
+            // The first line after `next_line` that starts with a word wrapped from the line
+            // above, which can be partly moved back
+            let Some((line_index, offset)) = (next_line..lines.len()).find_map(|line_index| {
+                let previous = &lines[line_index - 1];
+                let last = &glyphs[previous.glyph_end];
+                if last.parent == '\n' {
+                    return None;
+                }
+                let used_width = pen_x(previous.glyph_end)
+                    + font
+                        .metrics_indexed(last.key.glyph_index, size)
+                        .advance_width
+                    - pen_x(previous.glyph_start);
+                let remaining_width = max_width - used_width - hyphen_width;
+
+                let word_start = glyphs[lines[line_index].glyph_start].byte_offset;
+                let word_end = text[word_start..]
+                    .find(char::is_whitespace)
+                    .map_or(text.len(), |end| word_start + end);
+                let word = &text[word_start..word_end];
+                hyphenation(word)
+                    .into_iter()
+                    .filter(|&split| split > 0 && split < word.len())
+                    .filter(|&split| word.is_char_boundary(split))
+                    .filter(|&split| advance(&word[..split]) <= remaining_width)
+                    .max()
+                    .map(|split| (line_index, word_start + split))
+            }) else {
+                break;
+            };
+            next_line = line_index + 1;
+
+            // A visible hyphen and a hard break, fontdue would always draw a soft hyphen
+            text.insert_str(offset, "-\n");
+            let logical_offset = logical[offset];
+            logical.splice(offset..offset, [logical_offset; 2]);
+        }
+        (text.len() > area.text.len()).then_some(Self { text, logical })
+    }
+
+    /// Byte offset into the original text of the glyph at `offset` in the laid out text.
+    /// Inserted hyphens and line breaks belong to the char after them.
+    pub fn logical_offset(&self, offset: usize) -> usize {
+        self.logical[offset]
+    }
+}
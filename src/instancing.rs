@@ -0,0 +1,214 @@
+use std::hash::Hash;
+
+use ahash::{HashMap, HashSet};
+use wgpu::{Device, Queue};
+
+use crate::{
+    area::OverflowWrap, BlendMode, FontStyle, MaterialHandle, TextAreaEntry, TextAreaHandle,
+};
+
+// Offset of one instance relative to the text area whose vertices are drawn
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub(crate) struct InstanceOffset {
+    offset: [f32; 2],
+}
+
+impl InstanceOffset {
+    const ATTRIBS: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![3 => Float32x2];
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+/// Text areas that produce the same vertices apart from their position.
+/// Only the first area of a group (the leader) is drawn, once per group member,
+/// with the position differences passed as instance offsets.
+pub(crate) struct InstanceGroups {
+    dirty: bool,
+    // Leaders and the instance range they are drawn with
+    leaders: HashMap<TextAreaHandle, std::ops::Range<u32>>,
+    // Group members that are drawn through their leader
+    followers: HashSet<TextAreaHandle>,
+    buffer: Option<wgpu::Buffer>,
+}
+
+impl InstanceGroups {
+    pub fn new() -> Self {
+        Self {
+            dirty: true,
+            leaders: HashMap::default(),
+            followers: HashSet::default(),
+            buffer: None,
+        }
+    }
+
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn update<F: Eq + Hash + Copy>(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        text_areas: &HashMap<TextAreaHandle, TextAreaEntry<F>>,
+        window_size: [u32; 2],
+    ) {
+        if !self.dirty {
+            return;
+        }
+        self.dirty = false;
+        self.leaders.clear();
+        self.followers.clear();
+
+        let mut groups: HashMap<_, Vec<(TextAreaHandle, [f32; 2])>> = HashMap::default();
+        for (&handle, entry) in text_areas {
+            if let Some(key) = instance_key(entry, window_size) {
+                let [x, y, _, _] = entry.area.pixel_rect(window_size);
+                groups.entry(key).or_default().push((handle, [x, y]));
+            }
+        }
+        let mut offsets = Vec::new();
+        for mut members in groups.into_values().filter(|members| members.len() > 1) {
+            // Sorting keeps the leader stable between updates
+            members.sort_by_key(|(handle, _)| *handle);
+            let (leader, [leader_x, leader_y]) = members[0];
+            let start = offsets.len() as u32;
+            for &(handle, [x, y]) in &members {
+                offsets.push(InstanceOffset {
+                    offset: [x - leader_x, y - leader_y],
+                });
+                if handle != leader {
+                    self.followers.insert(handle);
+                }
+            }
+            self.leaders.insert(leader, start..offsets.len() as u32);
+        }
+        if offsets.is_empty() {
+            return;
+        }
+        let contents: &[u8] = bytemuck::cast_slice(&offsets);
+        crate::reserve_vertex_buffer(
+            device,
+            &mut self.buffer,
+            "EasyText Instance Offset Buffer",
+            contents.len() as u64,
+        );
+        queue.write_buffer(self.buffer.as_ref().unwrap(), 0, contents);
+    }
+
+    /// The instance range to draw `handle` with, if it leads a group.
+    pub fn leader_instances(&self, handle: TextAreaHandle) -> Option<std::ops::Range<u32>> {
+        self.leaders.get(&handle).cloned()
+    }
+
+    /// Whether `handle` is drawn by the leader of its group.
+    pub fn is_follower(&self, handle: TextAreaHandle) -> bool {
+        self.followers.contains(&handle)
+    }
+
+    pub fn buffer(&self) -> Option<&wgpu::Buffer> {
+        self.buffer.as_ref()
+    }
+}
+
+// Everything that influences the vertices of a text area, except its position.
+// Areas with per-area extras (transforms, rounded corners, selections, cursors) are never instanced.
+#[derive(PartialEq, Eq, Hash)]
+struct InstanceKey<'a, F> {
+    text: &'a str,
+    font: F,
+    font_weight: u16,
+    font_style: FontStyle,
+    size: u32,
+    v_align: u8,
+    h_align: u8,
+    overflow_wrap: OverflowWrap,
+    width: u32,
+    height: u32,
+    line_height_factor: u32,
+    tab_width: u32,
+    paragraph_spacing: u32,
+    first_line_indent: u32,
+    scroll_y: u32,
+    opacity: u32,
+    chars_visible: Option<usize>,
+    wave_animation: Option<[u32; 4]>,
+    top_offset: u32,
+    left_offset: u32,
+    z_depth: u32,
+    color: [u32; 4],
+    outline: Option<(u32, [u32; 4])>,
+    vertical_layout: bool,
+    #[cfg(feature = "bidi")]
+    bidi: bool,
+    blend_mode: Option<BlendMode>,
+    material: Option<MaterialHandle>,
+}
+
+fn instance_key<F: Eq + Hash + Copy>(
+    entry: &TextAreaEntry<F>,
+    window_size: [u32; 2],
+) -> Option<InstanceKey<'_, F>> {
+    let area = &entry.area;
+    if !area.visible
+        || area.transform.is_some()
+        || area.corner_radius != 0.0
+        || area.selection.is_some()
+        || area.cursor_position.is_some()
+        || area.hyphenation.is_some()
+        || entry.vertex_count == 0
+        || entry.rect_vertex_count > 0
+        || entry.shadow_vertex_count > 0
+    {
+        return None;
+    }
+    let [_, _, width, height] = area.pixel_rect(window_size);
+    Some(InstanceKey {
+        text: &area.text,
+        font: area.font,
+        font_weight: area.font_weight,
+        font_style: area.font_style,
+        // The fitted size of `TextArea::shrink_to_fit`, which can differ for equal sizes
+        size: entry.font_size().to_bits(),
+        v_align: area.v_align as u8,
+        h_align: area.h_align as u8,
+        overflow_wrap: area.overflow_wrap,
+        width: width.to_bits(),
+        height: height.to_bits(),
+        line_height_factor: area.line_height_factor.to_bits(),
+        tab_width: area.tab_width.to_bits(),
+        paragraph_spacing: area.paragraph_spacing.to_bits(),
+        first_line_indent: area.first_line_indent.to_bits(),
+        scroll_y: area.scroll_y.to_bits(),
+        opacity: area.opacity.to_bits(),
+        chars_visible: area.typewriter.map(|typewriter| typewriter.chars_visible),
+        wave_animation: area.wave_animation.map(|wave| {
+            [
+                wave.amplitude,
+                wave.frequency,
+                wave.speed,
+                wave.phase_offset_per_char,
+            ]
+            .map(f32::to_bits)
+        }),
+        top_offset: area.top_offset.to_bits(),
+        left_offset: area.left_offset.to_bits(),
+        z_depth: area.z_depth.to_bits(),
+        color: area.color.map(f32::to_bits),
+        outline: area
+            .outline
+            .map(|outline| (outline.width.to_bits(), outline.color.map(f32::to_bits))),
+        vertical_layout: area.vertical_layout,
+        #[cfg(feature = "bidi")]
+        bidi: area.bidi,
+        blend_mode: area.blend_mode,
+        material: area.material,
+    })
+}
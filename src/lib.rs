@@ -1,35 +1,237 @@
-use std::hash::Hash;
+use std::{
+    borrow::Cow,
+    cell::Cell,
+    fmt,
+    fs::File,
+    future::Future,
+    hash::Hash,
+    io::{BufReader, BufWriter, Write},
+    marker::PhantomData,
+    ops::{Deref, DerefMut, Range},
+    path::Path,
+    sync::Arc,
+};
 
 use ahash::HashMap;
-use area::TextArea;
+use animation::{ColorAnimation, FadeAnimation};
+use area::{CoordinateMode, ImeComposition, OverflowWrap, TextArea, TextAreaBorder};
 use atlas::Atlas;
 use bytemuck::{Pod, Zeroable};
-pub use fontdue::layout::{HorizontalAlign, VerticalAlign};
+use family::{resolve_font, FontFamily, DEFAULT_FONT_WEIGHT};
+pub use fontdue::layout::{HorizontalAlign, VerticalAlign, WrapStyle};
 use fontdue::{
-    layout::{CoordinateSystem, Layout, LayoutSettings, TextStyle, WrapStyle},
+    layout::{CoordinateSystem, GlyphPosition, Layout, LayoutSettings, LinePosition, TextStyle},
     Font, FontSettings,
 };
-use wgpu::{
-    util::DeviceExt, BindGroup, Device, PipelineLayoutDescriptor, Queue, RenderPass,
-    RenderPipeline, RenderPipelineDescriptor, TextureFormat,
-};
+use hyphenation::HyphenatedText;
+use instancing::InstanceGroups;
+use pipelines::{PipelineSources, Pipelines};
+use quad_indices::QuadIndices;
+use staging::{StagedBuffer, StagingPool};
+use vertical::VerticalLayout;
+use wgpu::{util::DeviceExt, BindGroup, BindGroupLayout, Device, Queue, RenderPass, TextureFormat};
 
+#[cfg(feature = "accessibility")]
+mod accessibility;
+mod animation;
 pub mod area;
 mod atlas;
+#[cfg(feature = "bidi")]
+mod bidi;
+mod builder;
+pub mod easing;
+mod error;
+mod family;
+#[cfg(feature = "testing")]
+mod headless;
+mod hyphenation;
+mod instancing;
+mod material;
+mod metrics;
+mod pipelines;
+mod quad_indices;
+#[cfg(feature = "sdf")]
+mod sdf;
+mod staging;
+mod vertical;
+
+#[cfg(feature = "accessibility")]
+pub use accessibility::{AccessibilityNode, AccessibilityRole};
+pub use animation::ColorKeyframe;
+pub use atlas::AtlasPacker;
+pub use builder::{BlendMode, EasyTextBuilder};
+pub use error::EasyTextError;
+pub use family::{FontStyle, FontWeight};
+pub use material::{Material, MaterialHandle};
+pub use metrics::{FontMetricsResult, LayoutMetrics, LayoutQuerySettings};
+#[cfg(feature = "sdf")]
+pub use sdf::SdfSettings;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TextAreaHandle {
     id: u32,
+    // Ids of removed text areas are reused with the next generation,
+    // so stale handles never refer to a different text area
+    generation: u32,
+}
+
+impl TextAreaHandle {
+    /// Returns whether the text area of this handle still exists in `easy_text`.
+    pub fn is_valid<F: Eq + Hash + Copy, V: VertexFormat>(
+        &self,
+        easy_text: &EasyText<F, V>,
+    ) -> bool {
+        easy_text.text_areas.contains_key(self)
+    }
+
+    /// Returns the raw id of the text area, e.g. for logging. Ids of removed text areas are
+    /// reused, so only the handle itself identifies a text area uniquely.
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl fmt::Display for TextAreaHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "TextArea({})", self.id)
+    }
+}
+
+/// Refers to the pipelines of an additional color target format, see `EasyText::add_render_target`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderTargetHandle {
+    id: u32,
+}
+
+/// Mutable access to a text area, see `EasyText::text_area_mut`. The text area is marked
+/// dirty when it is dereferenced mutably, reading it through the guard is free.
+pub struct TextAreaGuard<'a, F: Eq + Hash + Copy, V: VertexFormat = GlyphVertex> {
+    easy_text: &'a mut EasyText<F, V>,
+    handle: TextAreaHandle,
+}
+
+impl<F: Eq + Hash + Copy, V: VertexFormat> TextAreaGuard<'_, F, V> {
+    /// Marks the text area dirty without changing it.
+    pub fn mark_dirty(&mut self) {
+        self.easy_text.mark_dirty(self.handle);
+    }
+}
+
+impl<F: Eq + Hash + Copy, V: VertexFormat> Deref for TextAreaGuard<'_, F, V> {
+    type Target = TextArea<F>;
+
+    fn deref(&self) -> &TextArea<F> {
+        &self.easy_text.text_areas[&self.handle].area
+    }
+}
+
+impl<F: Eq + Hash + Copy, V: VertexFormat> DerefMut for TextAreaGuard<'_, F, V> {
+    fn deref_mut(&mut self) -> &mut TextArea<F> {
+        self.easy_text.mark_dirty(self.handle);
+        &mut self
+            .easy_text
+            .text_areas
+            .get_mut(&self.handle)
+            .expect("Text area of a guard was removed")
+            .area
+    }
+}
+
+/// Position of a laid out glyph in pixel space, see `EasyText::glyph_positions`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphInfo<F> {
+    /// Index of the char in the text of the text area (not the byte offset)
+    pub char_index: usize,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    pub font: F,
+    /// Font size the glyph was laid out at, lower than `TextArea::size` if the area was shrunk
+    /// with `TextArea::shrink_to_fit`
+    pub size: f32,
+    /// Index of the line the glyph is on, or of the column for vertical text
+    pub line: usize,
+}
+
+/// Bounds of the part of a text range on one line, see `EasyText::find_text_positions`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlyphPositionRange {
+    pub start_x: f32,
+    pub start_y: f32,
+    pub end_x: f32,
+    pub end_y: f32,
+    pub line: usize,
+}
+
+/// Bounds of one line of a text area in pixels, see `EasyText::line_bounding_boxes`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineBBox {
+    /// Index of the line, or of the column for vertical text
+    pub line: usize,
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+    /// Byte range into the text of the chars on the line, including a trailing line break
+    pub char_range: Range<usize>,
+}
+
+/// One line of a text area as it is displayed, see `EasyText::visual_lines`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VisualLine {
+    pub y: f32,
+    pub height: f32,
+    /// Index range of the glyphs on the line
+    pub glyph_range: Range<usize>,
+    /// Byte range into the text of the chars on the line, including a trailing line break
+    pub char_range: Range<usize>,
+    /// Whether the line ends with a line break instead of being wrapped
+    pub hard_break_at_end: bool,
+}
+
+/// Which side of a wrapped line break a caret belongs to, see `CaretPosition`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Affinity {
+    /// At the end of the line, behind the char before the byte offset
+    Upstream,
+    /// In front of the char at the byte offset, at the start of its line
+    #[default]
+    Downstream,
+}
+
+/// A caret between two chars of a text area, see `EasyText::caret_from_click`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CaretPosition {
+    pub byte_offset: usize,
+    /// Index of the line the caret is shown on
+    pub line: usize,
+    /// Only `Upstream` at the end of a wrapped line, where the byte offset is also the start of the next line
+    pub affinity: Affinity,
+}
+
+/// Counters collected during one `render` call (or `prepare` and `draw`), see `EasyText::last_frame_stats`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    pub draw_calls: u32,
+    /// Vertices of all draw calls, counting every instance
+    pub total_vertices: u64,
+    /// Glyphs rasterized and written to the atlas
+    pub atlas_uploads: u32,
+    pub dirty_areas_processed: u32,
 }
 
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 struct DebugLineVertex {
     pos: [f32; 2],
+    color: [u8; 4],
 }
 
 impl DebugLineVertex {
-    const ATTRIBS: [wgpu::VertexAttribute; 1] = wgpu::vertex_attr_array![0 => Float32x3];
+    const ATTRIBS: [wgpu::VertexAttribute; 2] =
+        wgpu::vertex_attr_array![0 => Float32x2, 1 => Unorm8x4];
     fn desc() -> wgpu::VertexBufferLayout<'static> {
         use std::mem;
 
@@ -41,22 +243,375 @@ impl DebugLineVertex {
     }
 }
 
+// Position of a laid out glyph in the text of its area, which only differs from
+// the byte offset in the layout if the text was reordered for bidirectional text or hyphenated
+#[derive(Debug, Clone, Copy)]
+struct LogicalGlyph {
+    offset: usize,
+    rtl: bool,
+}
+
+// The text of an area as it is passed to the layout, after it was reordered or hyphenated,
+// or with the IME preedit text inserted
+struct VisualText {
+    #[cfg(feature = "bidi")]
+    bidi: Option<bidi::BidiText>,
+    hyphenated: Option<HyphenatedText>,
+    // Text with the preedit text inserted at the cursor, and the byte range of the preedit text
+    composed: Option<(String, Range<usize>)>,
+}
+
+impl VisualText {
+    fn new<F: Eq + Hash + Copy>(
+        layout: &mut Layout,
+        font: &Font,
+        area: &TextArea<F>,
+        size: f32,
+        window_size: [u32; 2],
+    ) -> Self {
+        // The composition is shown as it is typed, without reordering or hyphenation
+        if let (Some(composition), false) = (&area.ime_composition, area.vertical_layout) {
+            let offset = area
+                .cursor_position
+                .filter(|&offset| area.text.is_char_boundary(offset))
+                .unwrap_or(area.text.len());
+            let mut text = area.text.clone();
+            text.insert_str(offset, &composition.preedit_text);
+            let preedit = offset..offset + composition.preedit_text.len();
+            return Self {
+                #[cfg(feature = "bidi")]
+                bidi: None,
+                hyphenated: None,
+                composed: Some((text, preedit)),
+            };
+        }
+        #[cfg(feature = "bidi")]
+        let bidi = (area.bidi && !area.vertical_layout).then(|| bidi::BidiText::new(&area.text));
+        #[cfg(feature = "bidi")]
+        let reordered = bidi.is_some();
+        #[cfg(not(feature = "bidi"))]
+        let reordered = false;
+        // Hyphenation is skipped for reordered and vertical text, and can't happen without wrapping
+        let hyphenated = match &area.hyphenation {
+            Some(hyphenation) if !reordered && !area.vertical_layout && !area.auto_width => {
+                let [_, _, width, _] = area.pixel_rect(window_size);
+                HyphenatedText::new(layout, font, area, size, width, hyphenation)
+            }
+            _ => None,
+        };
+        Self {
+            #[cfg(feature = "bidi")]
+            bidi,
+            hyphenated,
+            composed: None,
+        }
+    }
+
+    // Byte range of the IME preedit text in the laid out text
+    fn preedit(&self) -> Option<Range<usize>> {
+        self.composed.as_ref().map(|(_, preedit)| preedit.clone())
+    }
+
+    // The text to lay out instead of the text of the area, if it was changed
+    fn text(&self) -> Option<&str> {
+        if let Some((text, _)) = &self.composed {
+            return Some(text);
+        }
+        #[cfg(feature = "bidi")]
+        if let Some(bidi) = &self.bidi {
+            return Some(&bidi.visual);
+        }
+        self.hyphenated.as_ref().map(|text| text.text.as_str())
+    }
+
+    fn logical_glyph(&self, offset: usize) -> LogicalGlyph {
+        // The preedit text belongs to the char at the cursor
+        if let Some((_, preedit)) = &self.composed {
+            let offset = match offset {
+                offset if offset < preedit.start => offset,
+                offset if offset < preedit.end => preedit.start,
+                offset => offset - preedit.len(),
+            };
+            return LogicalGlyph { offset, rtl: false };
+        }
+        #[cfg(feature = "bidi")]
+        if let Some(bidi) = &self.bidi {
+            return bidi.logical_glyph(offset);
+        }
+        let offset = match &self.hyphenated {
+            Some(hyphenated) => hyphenated.logical_offset(offset),
+            None => offset,
+        };
+        LogicalGlyph { offset, rtl: false }
+    }
+}
+
+// Outlines of rectangles, drawn with the debug line pipeline. Also holds the triangles of
+// `TextArea::border`, which are drawn with the area border pipeline.
+struct DebugLines {
+    vertex_buffer: wgpu::Buffer,
+    index_buffer: wgpu::Buffer,
+    index_count: u32,
+}
+
+impl DebugLines {
+    // Returns `None` if there are no rectangles ([min_x, min_y, max_x, max_y]) to draw
+    fn new(
+        device: &Device,
+        label: &str,
+        rects: impl Iterator<Item = [f32; 4]>,
+        color: [u8; 4],
+    ) -> Option<Self> {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for (i, [x0, y0, x1, y1]) in rects.enumerate() {
+            vertices.extend_from_slice(&[
+                DebugLineVertex {
+                    pos: [x0, y0],
+                    color,
+                },
+                DebugLineVertex {
+                    pos: [x1, y0],
+                    color,
+                },
+                DebugLineVertex {
+                    pos: [x1, y1],
+                    color,
+                },
+                DebugLineVertex {
+                    pos: [x0, y1],
+                    color,
+                },
+            ]);
+            let i = i as u32 * 4;
+            indices.extend_from_slice(&[i, i + 1, i + 1, i + 2, i + 2, i + 3, i + 3, i]);
+        }
+        Self::from_vertices(device, label, &vertices, &indices)
+    }
+
+    // Triangles of the rings around `borders` ([min_x, min_y, max_x, max_y] of the area and its
+    // border), returns `None` if there are none
+    fn borders(
+        device: &Device,
+        borders: impl Iterator<Item = ([f32; 4], TextAreaBorder)>,
+    ) -> Option<Self> {
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        for (rect, border) in borders {
+            let color = border
+                .color
+                .map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+            let ring = border_ring(rect, &border);
+            let first = vertices.len() as u32;
+            let points = ring.len() as u32;
+            for (outer, inner) in ring {
+                vertices.push(DebugLineVertex { pos: outer, color });
+                vertices.push(DebugLineVertex { pos: inner, color });
+            }
+            for i in 0..points {
+                let [o0, i0] = [first + i * 2, first + i * 2 + 1];
+                let next = (i + 1) % points;
+                let [o1, i1] = [first + next * 2, first + next * 2 + 1];
+                indices.extend_from_slice(&[o0, i0, o1, i0, i1, o1]);
+            }
+        }
+        Self::from_vertices(device, "EasyText Area Borders", &vertices, &indices)
+    }
+
+    fn from_vertices(
+        device: &Device,
+        label: &str,
+        vertices: &[DebugLineVertex],
+        indices: &[u32],
+    ) -> Option<Self> {
+        if indices.is_empty() {
+            return None;
+        }
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Vertex Buffer", label)),
+            contents: bytemuck::cast_slice(vertices),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some(&format!("{} Index Buffer", label)),
+            contents: bytemuck::cast_slice(indices),
+            usage: wgpu::BufferUsages::INDEX,
+        });
+        Some(Self {
+            vertex_buffer,
+            index_buffer,
+            index_count: indices.len() as u32,
+        })
+    }
+
+    fn draw(&self, render_pass: &mut RenderPass, stats: &mut RenderStats) {
+        render_pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
+        render_pass.set_index_buffer(self.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+        render_pass.draw_indexed(0..self.index_count, 0, 0..1);
+        stats.draw_calls += 1;
+        stats.total_vertices += self.index_count as u64;
+    }
+}
+
+// Corner segments of rounded borders
+const BORDER_CORNER_SEGMENTS: u32 = 8;
+
+// Pairs of points on the outer and inner edge of a border around `rect`, clockwise
+// from the top left corner
+fn border_ring(rect: [f32; 4], border: &TextAreaBorder) -> Vec<([f32; 2], [f32; 2])> {
+    let width = border.width.max(0.0);
+    let [x0, y0, x1, y1] = [
+        rect[0] - width,
+        rect[1] - width,
+        rect[2] + width,
+        rect[3] + width,
+    ];
+    let radius = border.radius.clamp(0.0, (x1 - x0).min(y1 - y0) / 2.0);
+    let inner_radius = (radius - width).max(0.0);
+    let segments = if radius > 0.0 {
+        BORDER_CORNER_SEGMENTS
+    } else {
+        1
+    };
+    // Corners of the outer rect, with the direction towards the inside and the start angle
+    let corners = [
+        ([x0, y0], [1.0, 1.0], std::f32::consts::PI),
+        ([x1, y0], [-1.0, 1.0], std::f32::consts::PI * 1.5),
+        ([x1, y1], [-1.0, -1.0], 0.0),
+        ([x0, y1], [1.0, -1.0], std::f32::consts::FRAC_PI_2),
+    ];
+    let mut ring = Vec::new();
+    for ([x, y], [dx, dy], start) in corners {
+        let outer_center = [x + dx * radius, y + dy * radius];
+        // The inner corner is rounded less, or not at all for borders wider than the radius
+        let inset = width + inner_radius;
+        let inner_center = [x + dx * inset, y + dy * inset];
+        for segment in 0..=segments {
+            let angle = start + std::f32::consts::FRAC_PI_2 * segment as f32 / segments as f32;
+            let [cos, sin] = [angle.cos(), angle.sin()];
+            ring.push((
+                [
+                    outer_center[0] + cos * radius,
+                    outer_center[1] + sin * radius,
+                ],
+                [
+                    inner_center[0] + cos * inner_radius,
+                    inner_center[1] + sin * inner_radius,
+                ],
+            ));
+        }
+    }
+    ring
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, Debug, Pod, Zeroable)]
 struct MetaInfo {
     window_size: [u32; 2],
 }
 
+/// The default vertex of the glyph quads.
 #[repr(C)]
-#[derive(Copy, Clone, Debug, Pod, Zeroable)]
-struct GlyphVertex {
-    pos: [f32; 2],
-    tex_coord: [f32; 2],
+#[derive(Copy, Clone, Debug, PartialEq, Pod, Zeroable)]
+pub struct GlyphVertex {
+    /// Pixel position, the z component is `TextArea::z_depth`
+    pub pos: [f32; 3],
+    pub tex_coord: [f32; 2],
+    pub color: [f32; 4],
+}
+
+/// Vertex type of the glyph vertex buffers, to pass extra per-vertex data (e.g. a material id)
+/// to a custom shader. See `EasyTextBuilder::vertex_format`.
+pub trait VertexFormat: Pod + Zeroable {
+    /// Layout of the vertex buffer. The built-in shader reads the position, texture coordinate
+    /// and color from locations 0, 1 and 2 with the formats of `GlyphVertex`.
+    /// Location 3 is taken by the instance offset of instanced text areas.
+    fn desc() -> wgpu::VertexBufferLayout<'static>;
+
+    /// Creates the vertex for one corner of a quad of `glyph` (also used for its shadow and outline),
+    /// `vertex` is the `GlyphVertex` that would be used by default.
+    fn from_glyph<F>(glyph: &GlyphInfo<F>, vertex: GlyphVertex) -> Self;
+
+    /// WGSL source of a shader replacing the built-in glyph shader, with the same bind groups and
+    /// the entry points `vs_main`, `vs_instanced` and `fs_main` (`fs_sdf` with the `sdf` feature,
+    /// `fs_subpixel` with subpixel rendering, see `EasyTextBuilder::custom_shader`).
+    fn shader() -> Option<&'static str> {
+        None
+    }
+}
+
+impl VertexFormat for GlyphVertex {
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        GlyphVertex::desc()
+    }
+
+    fn from_glyph<F>(_glyph: &GlyphInfo<F>, vertex: GlyphVertex) -> Self {
+        vertex
+    }
 }
 
 impl GlyphVertex {
+    const ATTRIBS: [wgpu::VertexAttribute; 3] =
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x2, 2 => Float32x4];
+
+    // Two triangles covering `rect` ([min_x, min_y, max_x, max_y]) textured with `tex_rect`
+    fn quad(rect: [f32; 4], tex_rect: [f32; 4], z: f32, color: [f32; 4]) -> [Self; 6] {
+        let [x0, y0, x1, y1] = rect;
+        let [u0, v0, u1, v1] = tex_rect;
+        [
+            Self {
+                pos: [x0, y0, z],
+                tex_coord: [u0, v0],
+                color,
+            },
+            Self {
+                pos: [x1, y0, z],
+                tex_coord: [u1, v0],
+                color,
+            },
+            Self {
+                pos: [x1, y1, z],
+                tex_coord: [u1, v1],
+                color,
+            },
+            Self {
+                pos: [x0, y0, z],
+                tex_coord: [u0, v0],
+                color,
+            },
+            Self {
+                pos: [x1, y1, z],
+                tex_coord: [u1, v1],
+                color,
+            },
+            Self {
+                pos: [x0, y1, z],
+                tex_coord: [u0, v1],
+                color,
+            },
+        ]
+    }
+    fn desc() -> wgpu::VertexBufferLayout<'static> {
+        use std::mem;
+
+        wgpu::VertexBufferLayout {
+            array_stride: mem::size_of::<Self>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Vertex,
+            attributes: &Self::ATTRIBS,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct RectVertex {
+    pos: [f32; 3],
+    color: [f32; 4],
+}
+
+impl RectVertex {
     const ATTRIBS: [wgpu::VertexAttribute; 2] =
-        wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x2];
+        wgpu::vertex_attr_array![0 => Float32x3, 1 => Float32x4];
     fn desc() -> wgpu::VertexBufferLayout<'static> {
         use std::mem;
 
@@ -66,29 +621,182 @@ impl GlyphVertex {
             attributes: &Self::ATTRIBS,
         }
     }
+
+    // Two triangles covering `rect` ([min_x, min_y, max_x, max_y])
+    fn quad(rect: [f32; 4], z: f32, color: [f32; 4]) -> [Self; 6] {
+        let [x0, y0, x1, y1] = rect;
+        [[x0, y0], [x1, y0], [x1, y1], [x0, y0], [x1, y1], [x0, y1]].map(|[x, y]| Self {
+            pos: [x, y, z],
+            color,
+        })
+    }
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Pod, Zeroable)]
+struct AreaInfo {
+    transform: [[f32; 4]; 4],
+    // Pixel rect of the area, only used if `corner_radius` isn't 0
+    clip_rect: [f32; 4],
+    corner_radius: f32,
+    _padding: [f32; 3],
+}
+
+const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+// `transform` (column-major) applied around `pivot` instead of the origin
+fn transform_around(transform: [[f32; 4]; 4], [x, y]: [f32; 2]) -> [[f32; 4]; 4] {
+    let translation = |x: f32, y: f32| {
+        let mut matrix = IDENTITY;
+        matrix[3] = [x, y, 0.0, 1.0];
+        matrix
+    };
+    let multiply = |a: [[f32; 4]; 4], b: [[f32; 4]; 4]| {
+        std::array::from_fn(|column| {
+            std::array::from_fn(|row| (0..4).map(|k| a[k][row] * b[column][k]).sum())
+        })
+    };
+    multiply(translation(x, y), multiply(transform, translation(-x, -y)))
+}
+
+// A text area and the GPU resources prepared for it
+struct TextAreaEntry<F: Eq + Hash + Copy> {
+    area: TextArea<F>,
+    // Index of the face of the font family the area was prepared with, see `FontFamily::resolve`
+    font_face: u16,
+    // Buffers are reused while the vertices fit, so the counts are tracked separately
+    vertex_buffer: Option<wgpu::Buffer>,
+    vertex_count: u32,
+    // Solid quads drawn behind the glyphs, e.g. the selection highlight
+    rect_vertex_buffer: Option<wgpu::Buffer>,
+    rect_vertex_count: u32,
+    // Glyph quads in the shadow color, drawn before the glyphs
+    shadow_vertex_buffer: Option<wgpu::Buffer>,
+    shadow_vertex_count: u32,
+    // Bounds of all drawn glyphs, for the glyph bounds debug overlay
+    glyph_rects: Vec<[f32; 4]>,
+    // Positions of all laid out glyphs, including clipped ones
+    glyphs: Vec<GlyphInfo<F>>,
+    area_info_buffer: Option<wgpu::Buffer>,
+    area_info_bind_group: Option<BindGroup>,
+    fade: Option<FadeAnimation>,
+    color_animation: Option<ColorAnimation>,
+    // Requested and fitted size of the last `TextArea::shrink_to_fit` layout
+    fitted_size: Option<(f32, f32)>,
+}
+
+impl<F: Eq + Hash + Copy> TextAreaEntry<F> {
+    fn new(area: TextArea<F>) -> Self {
+        Self {
+            area,
+            font_face: 0,
+            vertex_buffer: None,
+            vertex_count: 0,
+            rect_vertex_buffer: None,
+            rect_vertex_count: 0,
+            shadow_vertex_buffer: None,
+            shadow_vertex_count: 0,
+            glyph_rects: Vec::new(),
+            glyphs: Vec::new(),
+            area_info_buffer: None,
+            area_info_bind_group: None,
+            fade: None,
+            color_animation: None,
+            fitted_size: None,
+        }
+    }
+
+    // Font size the text is laid out at, lowered from `TextArea::size` by `shrink_to_fit`
+    // until the requested size changes
+    fn font_size(&self) -> f32 {
+        match self.fitted_size {
+            Some((requested, fitted)) if requested == self.area.size => fitted,
+            _ => self.area.size,
+        }
+    }
 }
 
-pub struct EasyText<F: Eq + Hash + Copy> {
+#[cfg(not(feature = "sdf"))]
+const GLYPH_FRAGMENT_ENTRY_POINT: &str = "fs_main";
+#[cfg(feature = "sdf")]
+const GLYPH_FRAGMENT_ENTRY_POINT: &str = "fs_sdf";
+
+// Rasterizes the coverage of the red, green and blue subpixels of a horizontal RGB stripe
+// display into RGBA pixels, alpha is the highest of the three
+#[cfg(not(feature = "sdf"))]
+fn rasterize_subpixel(font: &Font, glyph_index: u16, size: f32) -> (fontdue::Metrics, Vec<u8>) {
+    let (metrics, rgb) = font.rasterize_indexed_subpixel(glyph_index, size);
+    let rgba = rgb
+        .chunks_exact(3)
+        .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], rgb[0].max(rgb[1]).max(rgb[2])])
+        .collect();
+    (metrics, rgba)
+}
+
+/// Comparator used to sort text areas before drawing them, see [`EasyText::set_draw_order_fn`].
+pub type DrawOrderFn<F> = Box<dyn Fn(&TextArea<F>, &TextArea<F>) -> std::cmp::Ordering>;
+
+// Seconds the cursor stays visible / hidden
+const CURSOR_BLINK_INTERVAL: f64 = 0.5;
+// Size in pixels of the dots underlining IME preedit text
+const IME_UNDERLINE_DOT: f32 = 2.0;
+
+pub struct EasyText<F: Eq + Hash + Copy, V: VertexFormat = GlyphVertex> {
     window_size: [u32; 2],
     meta_info: MetaInfo,
     meta_info_buffer_bind_group: BindGroup,
     meta_info_buffer: wgpu::Buffer,
-    atlas: Atlas<F>,
+    area_info_bind_group_layout: BindGroupLayout,
+    // Keyed by font id and face index
+    atlas: Atlas<(F, u16)>,
     debug_show_atlas: bool,
-    debug_show_atlas_pipeline: RenderPipeline,
     debug_show_area_borders: bool,
-    debug_show_area_borders_pipeline: RenderPipeline,
-    debug_show_area_borders_vertex_buffer: Option<wgpu::Buffer>,
-    debug_show_area_borders_vertex_count: u32,
-    debug_show_area_borders_index_buffer: Option<wgpu::Buffer>,
-    debug_show_area_borders_index_count: u32,
+    debug_show_area_borders_lines: Option<DebugLines>,
+    debug_border_color: [u8; 4],
+    debug_show_glyph_bounds: bool,
+    debug_show_glyph_bounds_lines: Option<DebugLines>,
+    // Triangles of all `TextArea::border`s, `None` if they have to be created again
+    area_borders: Option<DebugLines>,
 
-    fonts: HashMap<F, Font>,
+    fonts: HashMap<F, FontFamily>,
     next_text_area_id: u32,
-    text_areas: HashMap<TextAreaHandle, (TextArea<F>, Option<wgpu::Buffer>)>,
+    // Handles of removed text areas, with their generation already advanced
+    free_text_area_handles: Vec<TextAreaHandle>,
+    text_areas: HashMap<TextAreaHandle, TextAreaEntry<F>>,
+    text_area_names: HashMap<String, TextAreaHandle>,
     dirty_text_areas: Vec<TextAreaHandle>,
-    render_pipeline: RenderPipeline,
+    staging_pool: StagingPool,
+    // Set if glyph quads are drawn indexed with 4 instead of 6 vertices
+    quad_indices: Option<QuadIndices>,
+    // A cell, so `draw` can add its counters without a mutable reference
+    last_frame_stats: Cell<RenderStats>,
+    #[cfg(feature = "sdf")]
+    sdf_settings: SdfSettings,
+    pipeline_sources: PipelineSources,
+    // Pipelines for the surface format passed to `new`
+    pipelines: Pipelines,
+    // Pipelines for the formats added with `add_render_target`, indexed by `RenderTargetHandle`
+    render_targets: Vec<Pipelines>,
+    instance_groups: InstanceGroups,
     layout: Layout,
+    cursor_blink_timer: f64,
+    cursor_blink_visible: bool,
+    // Seconds passed in `tick_animations`
+    animation_time: f64,
+    // `now_secs` of the last `tick` call
+    last_tick_time: Option<f64>,
+    on_fade_complete: Option<fn(TextAreaHandle)>,
+    draw_order: Option<DrawOrderFn<F>>,
+    // Indexed by `MaterialHandle`, kept to create their pipelines for new render targets
+    materials: Vec<Material>,
+    #[cfg(feature = "testing")]
+    headless: Option<headless::Headless>,
+    _vertex: PhantomData<V>,
 }
 
 impl<F: Eq + Hash + Copy> EasyText<F> {
@@ -98,7 +806,39 @@ impl<F: Eq + Hash + Copy> EasyText<F> {
         device: &Device,
         surface_format: TextureFormat,
     ) -> Self {
-        let atlas = Atlas::new(device);
+        EasyTextBuilder::new(window_width, window_height, surface_format).build(device)
+    }
+
+    /// Like `new` followed by `add_font` for every font, but returns an error instead of
+    /// panicking if one of the fonts can't be parsed.
+    pub fn with_fonts(
+        window_width: u32,
+        window_height: u32,
+        device: &Device,
+        surface_format: TextureFormat,
+        fonts: impl IntoIterator<Item = (F, Vec<u8>)>,
+    ) -> Result<Self, EasyTextError> {
+        let mut easy_text = Self::new(window_width, window_height, device, surface_format);
+        for (font_id, raw_file_content) in fonts {
+            easy_text.try_add_font(font_id, raw_file_content)?;
+        }
+        Ok(easy_text)
+    }
+
+    pub fn builder(
+        window_width: u32,
+        window_height: u32,
+        surface_format: TextureFormat,
+    ) -> EasyTextBuilder<F> {
+        EasyTextBuilder::new(window_width, window_height, surface_format)
+    }
+}
+
+impl<F: Eq + Hash + Copy, V: VertexFormat> EasyText<F, V> {
+    pub(crate) fn from_builder(builder: EasyTextBuilder<F, V>, device: &Device) -> Self {
+        let [window_width, window_height] = builder.window_size;
+        let surface_format = builder.surface_format;
+        let atlas = Atlas::new(device, &builder.atlas);
         let meta_info = MetaInfo {
             window_size: [window_width, window_height],
         };
@@ -130,182 +870,71 @@ impl<F: Eq + Hash + Copy> EasyText<F> {
             label: Some("EasyText Meta Info Bind Group"),
         });
 
-        // DEBUG SHOW ATLAS
-        let debug_show_atlas_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("EasyText Debug Show Atlas Shader"),
-            source: wgpu::ShaderSource::Wgsl(
-                include_str!("./shaders/debug_show_atlas.wgsl").into(),
-            ),
-        });
-        let debug_show_atlas_pipeline_layout =
-            device.create_pipeline_layout(&PipelineLayoutDescriptor {
-                label: Some("EasyText Debug Show Atlas Pipeline Layout"),
-                bind_group_layouts: &[&atlas.texture_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-        let debug_show_atlas_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("EasyText Render Pipeline"),
-            layout: Some(&debug_show_atlas_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &debug_show_atlas_shader,
-                entry_point: "vs_main",
-                buffers: &[],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &debug_show_atlas_shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_format,
-                    blend: Some(wgpu::BlendState::REPLACE),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleStrip,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-            cache: None,
-        });
-
-        // DEBUG DRAW AREA BORDERS
-        let debug_show_area_borders_shader =
-            device.create_shader_module(wgpu::ShaderModuleDescriptor {
-                label: Some("EasyText Debug Show TextArea Borders Shader"),
-                source: wgpu::ShaderSource::Wgsl(
-                    include_str!("./shaders/debug_show_area_borders.wgsl").into(),
-                ),
-            });
-        let debug_show_area_borders_pipeline_layout =
-            device.create_pipeline_layout(&PipelineLayoutDescriptor {
-                label: Some("EasyText Debug Show TextArea Borders Pipeline Layout"),
-                bind_group_layouts: &[&meta_info_buffer_bind_group_layout],
-                push_constant_ranges: &[],
-            });
-        let debug_show_area_borders_pipeline =
-            device.create_render_pipeline(&RenderPipelineDescriptor {
-                label: Some("EasyText Render Pipeline"),
-                layout: Some(&debug_show_area_borders_pipeline_layout),
-                vertex: wgpu::VertexState {
-                    module: &debug_show_area_borders_shader,
-                    entry_point: "vs_main",
-                    buffers: &[DebugLineVertex::desc()],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                },
-                fragment: Some(wgpu::FragmentState {
-                    module: &debug_show_area_borders_shader,
-                    entry_point: "fs_main",
-                    targets: &[Some(wgpu::ColorTargetState {
-                        format: surface_format,
-                        blend: Some(wgpu::BlendState::REPLACE),
-                        write_mask: wgpu::ColorWrites::ALL,
-                    })],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
-                }),
-                primitive: wgpu::PrimitiveState {
-                    topology: wgpu::PrimitiveTopology::LineList,
-                    strip_index_format: None,
-                    front_face: wgpu::FrontFace::Cw,
-                    cull_mode: None,
-                    polygon_mode: wgpu::PolygonMode::Fill,
-                    unclipped_depth: false,
-                    conservative: false,
-                },
-                depth_stencil: None,
-                multisample: wgpu::MultisampleState {
-                    count: 1,
-                    mask: !0,
-                    alpha_to_coverage_enabled: false,
-                },
-                multiview: None,
-                cache: None,
+        let area_info_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("EasyText Area Info Bind Group Layout"),
             });
 
-        // RENDER PIPELINE
-        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
-            label: Some("EasyText Shader"),
-            source: wgpu::ShaderSource::Wgsl(include_str!("./shaders/shader.wgsl").into()),
-        });
-        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
-            label: Some("EasyText Atlas Render Pipeline Layout"),
-            bind_group_layouts: &[
-                &atlas.texture_bind_group_layout,
-                &meta_info_buffer_bind_group_layout,
-            ],
-            push_constant_ranges: &[],
-        });
-        let render_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
-            label: Some("EasyText Render Pipeline"),
-            layout: Some(&render_pipeline_layout),
-            vertex: wgpu::VertexState {
-                module: &shader,
-                entry_point: "vs_main",
-                buffers: &[GlyphVertex::desc()],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            },
-            fragment: Some(wgpu::FragmentState {
-                module: &shader,
-                entry_point: "fs_main",
-                targets: &[Some(wgpu::ColorTargetState {
-                    format: surface_format,
-                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
-                    write_mask: wgpu::ColorWrites::ALL,
-                })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
-            }),
-            primitive: wgpu::PrimitiveState {
-                topology: wgpu::PrimitiveTopology::TriangleList,
-                strip_index_format: None,
-                front_face: wgpu::FrontFace::Cw,
-                cull_mode: None,
-                polygon_mode: wgpu::PolygonMode::Fill,
-                unclipped_depth: false,
-                conservative: false,
-            },
-            depth_stencil: None,
-            multisample: wgpu::MultisampleState {
-                count: 1,
-                mask: !0,
-                alpha_to_coverage_enabled: false,
-            },
-            multiview: None,
-            cache: None,
-        });
+        let pipeline_sources = PipelineSources::new(
+            device,
+            atlas.texture_bind_group_layout(),
+            &meta_info_buffer_bind_group_layout,
+            &area_info_bind_group_layout,
+            &builder,
+        );
+        let pipelines = Pipelines::new::<V>(device, &pipeline_sources, surface_format);
 
         Self {
             window_size: [window_width, window_height],
             meta_info,
             meta_info_buffer,
             meta_info_buffer_bind_group,
+            area_info_bind_group_layout,
             atlas,
             debug_show_atlas: false,
-            debug_show_atlas_pipeline,
             debug_show_area_borders: false,
-            debug_show_area_borders_pipeline,
-            debug_show_area_borders_vertex_buffer: None,
-            debug_show_area_borders_vertex_count: 0,
-            debug_show_area_borders_index_buffer: None,
-            debug_show_area_borders_index_count: 0,
+            debug_show_area_borders_lines: None,
+            debug_border_color: [255, 255, 255, 255],
+            debug_show_glyph_bounds: false,
+            debug_show_glyph_bounds_lines: None,
+            area_borders: None,
 
             fonts: HashMap::default(),
             next_text_area_id: 0,
+            free_text_area_handles: Vec::new(),
             text_areas: HashMap::default(),
+            text_area_names: HashMap::default(),
             dirty_text_areas: Vec::new(),
-            render_pipeline,
+            staging_pool: StagingPool::new(),
+            quad_indices: builder.use_index_buffer.then(QuadIndices::new),
+            last_frame_stats: Cell::default(),
+            #[cfg(feature = "sdf")]
+            sdf_settings: builder.sdf_settings,
+            pipeline_sources,
+            pipelines,
+            render_targets: Vec::new(),
+            instance_groups: InstanceGroups::new(),
             layout: Layout::new(CoordinateSystem::PositiveYDown),
+            cursor_blink_timer: 0.0,
+            cursor_blink_visible: true,
+            animation_time: 0.0,
+            last_tick_time: None,
+            on_fade_complete: None,
+            draw_order: None,
+            materials: Vec::new(),
+            #[cfg(feature = "testing")]
+            headless: None,
+            _vertex: PhantomData,
         }
     }
 
@@ -322,6 +951,20 @@ impl<F: Eq + Hash + Copy> EasyText<F> {
             0,
             bytemuck::cast_slice(&[self.meta_info]),
         );
+        // Normalized areas have to be laid out again for the new window size
+        let normalized: Vec<_> = self
+            .text_areas
+            .iter()
+            .filter(|(_, entry)| entry.area.coordinate_system == CoordinateMode::Normalized)
+            .map(|(&handle, _)| handle)
+            .collect();
+        if !normalized.is_empty() {
+            for handle in normalized {
+                self.insert_dirty(handle);
+            }
+            self.debug_show_area_borders_lines = None;
+            self.area_borders = None;
+        }
     }
 
     pub fn toggle_debug_show_atlas(&mut self) {
@@ -332,90 +975,1245 @@ impl<F: Eq + Hash + Copy> EasyText<F> {
         self.debug_show_area_borders = !self.debug_show_area_borders;
     }
 
+    /// Sets the color of the lines drawn by the area borders debug overlay.
+    pub fn set_debug_border_color(&mut self, color: [f32; 4]) {
+        self.debug_border_color = color.map(|c| (c.clamp(0.0, 1.0) * 255.0).round() as u8);
+        self.debug_show_area_borders_lines = None;
+    }
+
+    pub fn toggle_debug_show_glyph_bounds(&mut self) {
+        self.debug_show_glyph_bounds = !self.debug_show_glyph_bounds;
+    }
+
     pub fn add_font(&mut self, font_id: F, raw_file_content: Vec<u8>) {
+        self.try_add_font(font_id, raw_file_content).unwrap();
+    }
+
+    /// Like `add_font`, but parses the font straight from `data` instead of taking ownership
+    /// of a copy, e.g. for font files shared by several `EasyText` instances. fontdue keeps
+    /// the parsed glyph data, not the file, so `data` can be dropped afterwards.
+    pub fn add_font_shared(&mut self, font_id: F, data: Arc<[u8]>) {
+        self.try_add_font(font_id, &*data).unwrap();
+    }
+
+    /// Ids of all added fonts and font families, in no particular order.
+    pub fn loaded_font_ids(&self) -> Vec<F> {
+        self.fonts.keys().copied().collect()
+    }
+
+    pub fn is_font_loaded(&self, font_id: F) -> bool {
+        self.fonts.contains_key(&font_id)
+    }
+
+    /// Returns whether the font has a glyph for `c`, `false` if the font was not added.
+    /// Checks the regular face of font families.
+    pub fn font_has_char(&self, font_id: F, c: char) -> bool {
+        self.fonts
+            .get(&font_id)
+            .and_then(FontFamily::regular)
+            .is_some_and(|font| font.lookup_glyph_index(c) != 0)
+    }
+
+    /// Returns whether the font has a glyph for every char of `text`, including control
+    /// characters like line breaks, which most fonts don't have glyphs for.
+    pub fn font_has_chars(&self, font_id: F, text: &str) -> bool {
+        let Some(font) = self.fonts.get(&font_id).and_then(FontFamily::regular) else {
+            return false;
+        };
+        text.chars().all(|c| font.lookup_glyph_index(c) != 0)
+    }
+
+    /// Removes a font (or font family) and evicts its glyphs from the atlas.
+    /// Returns `EasyTextError::FontInUse` if a text area still uses it.
+    pub fn remove_font(&mut self, font_id: F) -> Result<(), EasyTextError> {
+        if self
+            .text_areas
+            .values()
+            .any(|entry| entry.area.font == font_id)
+        {
+            return Err(EasyTextError::FontInUse);
+        }
+        self.fonts.remove(&font_id);
+        self.atlas.remove_fonts(|(removed, _)| removed == font_id);
+        Ok(())
+    }
+
+    /// Reads the font file at `path` and adds it like `add_font`.
+    pub fn add_font_from_path(&mut self, font_id: F, path: &Path) -> Result<(), EasyTextError> {
+        let raw_file_content = std::fs::read(path)?;
+        self.try_add_font(font_id, raw_file_content)
+    }
+
+    fn try_add_font(
+        &mut self,
+        font_id: F,
+        raw_file_content: impl Deref<Target = [u8]>,
+    ) -> Result<(), EasyTextError> {
+        let font = Font::from_bytes(raw_file_content, FontSettings::default())
+            .map_err(EasyTextError::InvalidFontData)?;
         self.fonts.insert(
             font_id,
-            Font::from_bytes(raw_file_content, FontSettings::default()).unwrap(),
+            FontFamily::new(vec![(DEFAULT_FONT_WEIGHT, FontStyle::Normal, font)]),
         );
+        Ok(())
     }
 
-    pub fn add_text_area(&mut self, text_area: TextArea<F>) -> TextAreaHandle {
-        let id = self.next_text_area_id;
-        self.next_text_area_id += 1;
-        let handle = TextAreaHandle { id };
-        self.text_areas.insert(handle, (text_area, None));
-        if let Err(index) = self.dirty_text_areas.binary_search(&handle) {
-            self.dirty_text_areas.insert(index, handle);
-        }
-        self.debug_show_area_borders_vertex_buffer = None;
-        self.debug_show_area_borders_index_buffer = None;
-        handle
+    /// Adds the faces of a font family under one id, e.g. regular, bold and italic.
+    /// Text areas pick the face closest to their `font_weight` and `font_style`,
+    /// fonts added with `add_font` are a family with a single regular face.
+    /// Returns `EasyTextError::InvalidFontData` if any of the faces can't be parsed,
+    /// the family is not added then.
+    pub fn add_font_family(
+        &mut self,
+        family_id: F,
+        fonts: Vec<(FontWeight, FontStyle, Vec<u8>)>,
+    ) -> Result<(), EasyTextError> {
+        let faces = fonts
+            .into_iter()
+            .map(|(weight, style, raw_file_content)| {
+                let font = Font::from_bytes(raw_file_content, FontSettings::default())
+                    .map_err(EasyTextError::InvalidFontData)?;
+                Ok((weight, style, font))
+            })
+            .collect::<Result<_, EasyTextError>>()?;
+        self.fonts.insert(family_id, FontFamily::new(faces));
+        Ok(())
     }
 
-    pub fn remove_text_area(&mut self, handle: TextAreaHandle) {
-        self.debug_show_area_borders_vertex_buffer = None;
-        self.debug_show_area_borders_index_buffer = None;
-        self.text_areas.remove(&handle);
+    /// Awaits the raw file content of a font and adds it like `add_font_from_path`. Works with
+    /// any async source, e.g. `tokio::fs::read(path)` or a `fetch` in the browser mapped to an
+    /// I/O error.
+    pub async fn add_font_async(
+        &mut self,
+        font_id: F,
+        raw_file_content: impl Future<Output = std::io::Result<Vec<u8>>>,
+    ) -> Result<(), EasyTextError> {
+        let raw_file_content = raw_file_content.await?;
+        self.try_add_font(font_id, raw_file_content)
     }
 
-    pub fn text_area_mut(&mut self, handle: TextAreaHandle) -> Option<&mut TextArea<F>> {
-        if let Err(index) = self.dirty_text_areas.binary_search(&handle) {
-            self.dirty_text_areas.insert(index, handle);
-        }
-        self.debug_show_area_borders_vertex_buffer = None;
-        self.debug_show_area_borders_index_buffer = None;
-        self.text_areas.get_mut(&handle).map(|(area, _)| area)
+    /// Returns the metrics of a font at `size`, or `None` if the font was not added.
+    /// Uses the regular face of font families.
+    pub fn font_metrics(&self, font_id: F, size: f32) -> Option<FontMetricsResult<'_>> {
+        self.fonts
+            .get(&font_id)
+            .and_then(FontFamily::regular)
+            .map(|font| FontMetricsResult::new(font, size))
     }
 
-    pub fn text_area(&self, handle: TextAreaHandle) -> Option<&TextArea<F>> {
-        self.text_areas.get(&handle).map(|(area, _)| area)
+    /// Lays out `text` without rasterizing it, e.g. to find out how many lines it needs.
+    /// Returns `None` if the font was not added.
+    pub fn layout_metrics(
+        &self,
+        font_id: F,
+        text: &str,
+        settings: LayoutQuerySettings,
+    ) -> Option<LayoutMetrics> {
+        self.fonts
+            .get(&font_id)
+            .and_then(FontFamily::regular)
+            .map(|font| LayoutMetrics::new(font, text, &settings))
     }
 
-    pub fn render(&mut self, device: &Device, queue: &Queue, render_pass: &mut RenderPass) {
-        for handle in self.dirty_text_areas.drain(..) {
-            let (area, vertex_buffer) = match self.text_areas.get_mut(&handle) {
-                Some(area) => area,
-                None => continue,
-            };
-            let font = self.fonts.get(&area.font).expect("Font not found");
-            let layout_settings = LayoutSettings {
-                x: area.x,
-                y: area.y,
-                max_width: Some(area.width),
-                max_height: Some(area.height),
-                horizontal_align: area.h_align,
-                vertical_align: area.v_align,
-                line_height: area.line_height_factor,
-                wrap_style: WrapStyle::Word,
-                wrap_hard_breaks: true,
-            };
-            self.layout.reset(&layout_settings);
-            self.layout.append(
-                &[font],
-                &TextStyle {
-                    text: &area.text,
-                    px: area.size,
-                    font_index: 0,
-                    user_data: (),
-                },
-            );
-            let size = area.size;
-            let mut vertices = Vec::new();
-            for glyph in self.layout.glyphs() {
-                let prepared_glyph =
-                    match self
-                        .atlas
-                        .get(area.font, size as u16, glyph.key.glyph_index)
-                    {
-                        Some(glyph) => glyph,
-                        None => {
-                            let (metrics, bitmap) =
-                                font.rasterize_indexed(glyph.key.glyph_index, size);
-                            self.atlas.insert(
+    /// Reads the glyph atlas back from the GPU and returns it as a grayscale PNG file,
+    /// e.g. for debugging. Blocks until the GPU finished all submitted work.
+    #[cfg(feature = "export")]
+    pub fn export_atlas_png(&self, device: &Device, queue: &Queue) -> Vec<u8> {
+        self.atlas.export_png(device, queue)
+    }
+
+    /// Reads the glyph atlas back from the GPU, e.g. to check its contents in tests.
+    /// Returns the R8 pixels (RGBA8 with subpixel rendering) of all atlas pages side by side
+    /// and the size of that image.
+    /// Blocks until the GPU finished all submitted work.
+    #[cfg(feature = "testing")]
+    pub fn read_atlas_pixels_cpu(&self, device: &Device, queue: &Queue) -> (Vec<u8>, [u32; 2]) {
+        self.atlas.read_pixels_cpu(device, queue)
+    }
+
+    /// Saves all glyphs currently in the atlas to `path`, so they don't have to be
+    /// rasterized again on the next start. See `load_atlas_cache`.
+    pub fn save_atlas_cache(&self, path: &Path) -> Result<(), EasyTextError> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.atlas.write_cache(&mut writer, |(font_id, face)| {
+            let font = self.fonts.get(&font_id)?.face(face)?;
+            Some(font.file_hash() as u64)
+        })?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Loads glyphs saved by `save_atlas_cache` into the atlas.
+    /// Fonts are matched by a hash of their file content, so fonts have to be added before loading.
+    /// Glyphs of fonts that are not loaded are skipped.
+    pub fn load_atlas_cache(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        path: &Path,
+    ) -> Result<(), EasyTextError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let font_ids: HashMap<u64, (F, u16)> = self
+            .fonts
+            .iter()
+            .flat_map(|(&font_id, family)| {
+                family
+                    .faces()
+                    .map(move |(face, font)| (font.file_hash() as u64, (font_id, face)))
+            })
+            .collect();
+        self.atlas.read_cache(device, queue, &mut reader, |hash| {
+            font_ids.get(&hash).copied()
+        })
+    }
+
+    fn next_handle(&mut self) -> TextAreaHandle {
+        self.free_text_area_handles.pop().unwrap_or_else(|| {
+            let id = self.next_text_area_id;
+            self.next_text_area_id += 1;
+            TextAreaHandle { id, generation: 0 }
+        })
+    }
+
+    // Called for every handle whose text area was removed
+    fn free_handle(&mut self, handle: TextAreaHandle) {
+        self.free_text_area_handles.push(TextAreaHandle {
+            id: handle.id,
+            generation: handle.generation.wrapping_add(1),
+        });
+    }
+
+    pub fn add_text_area(&mut self, text_area: TextArea<F>) -> TextAreaHandle {
+        let handle = self.next_handle();
+        self.text_areas
+            .insert(handle, TextAreaEntry::new(text_area));
+        self.insert_dirty(handle);
+        self.debug_show_area_borders_lines = None;
+        self.area_borders = None;
+        handle
+    }
+
+    /// Adds a text area that can also be looked up by `name`, see `get_handle_by_name`.
+    /// A name that is already in use is reassigned to the new text area.
+    pub fn add_named_text_area(
+        &mut self,
+        name: impl Into<String>,
+        text_area: TextArea<F>,
+    ) -> TextAreaHandle {
+        let handle = self.add_text_area(text_area);
+        self.text_area_names.insert(name.into(), handle);
+        handle
+    }
+
+    pub fn get_handle_by_name(&self, name: &str) -> Option<TextAreaHandle> {
+        self.text_area_names.get(name).copied()
+    }
+
+    /// Removes the text area named `name`, returns false if there is none.
+    pub fn remove_named_text_area(&mut self, name: &str) -> bool {
+        match self.text_area_names.remove(name) {
+            Some(handle) => {
+                self.remove_text_area(handle);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Adds multiple text areas at once, returning their handles in the same order.
+    /// Cheaper than calling `add_text_area` for every area.
+    pub fn add_text_areas(
+        &mut self,
+        text_areas: impl IntoIterator<Item = TextArea<F>>,
+    ) -> Vec<TextAreaHandle> {
+        let handles: Vec<_> = text_areas
+            .into_iter()
+            .map(|text_area| {
+                let handle = self.next_handle();
+                self.text_areas
+                    .insert(handle, TextAreaEntry::new(text_area));
+                handle
+            })
+            .collect();
+        self.dirty_text_areas.extend_from_slice(&handles);
+        self.dirty_text_areas.sort_unstable();
+        self.dirty_text_areas.dedup();
+        self.instance_groups.mark_dirty();
+        self.debug_show_area_borders_lines = None;
+        self.area_borders = None;
+        handles
+    }
+
+    /// Returns the glyph positions computed by the last `render` call for a text area.
+    /// The positions are empty until the text area has been rendered once.
+    pub fn glyph_positions(&self, handle: TextAreaHandle) -> Option<&[GlyphInfo<F>]> {
+        self.text_areas
+            .get(&handle)
+            .map(|entry| entry.glyphs.as_slice())
+    }
+
+    /// Returns the bounds of every line of a text area, before `TextArea::transform`.
+    /// Lines span from the ascent to the descent of the font (columns of vertical text from
+    /// their highest to their lowest glyph) and from the pen position of their first glyph to
+    /// the advance of their last one. Lines are split at line breaks and wherever the text was
+    /// wrapped. Returns `None` if the area or its font don't exist.
+    pub fn line_bounding_boxes(&self, handle: TextAreaHandle) -> Option<Vec<LineBBox>> {
+        let lines = self.line_layout(self.text_areas.get(&handle)?)?;
+        Some(lines.into_iter().map(|(_, bbox)| bbox).collect())
+    }
+
+    /// Returns the visual lines of a text area, which differ from the lines of its text
+    /// (the logical lines) wherever the text was wrapped. The lines are taken from the layout,
+    /// so glyphs of different sizes or raised by a baseline offset still share their line.
+    /// Uses the current text, `glyph_range` indexes `glyph_positions` once it was rendered.
+    /// Returns `None` if the area or its font don't exist.
+    pub fn visual_lines(&self, handle: TextAreaHandle) -> Option<Vec<VisualLine>> {
+        let entry = self.text_areas.get(&handle)?;
+        let area = &entry.area;
+        let lines = self.line_layout(entry)?;
+        let visual_lines = lines
+            .into_iter()
+            .map(|(glyph_range, bbox)| VisualLine {
+                y: bbox.y,
+                height: bbox.height,
+                glyph_range,
+                hard_break_at_end: area.text[bbox.char_range.clone()].ends_with('\n'),
+                char_range: bbox.char_range,
+            })
+            .collect();
+        Some(visual_lines)
+    }
+
+    // Glyph range and bounds of every line of the area of `entry`, see `line_bounding_boxes`
+    fn line_layout(&self, entry: &TextAreaEntry<F>) -> Option<Vec<(Range<usize>, LineBBox)>> {
+        let area = &entry.area;
+        let size = entry.font_size();
+        let (_, font) = resolve_font(&self.fonts, area)?;
+        let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
+        let visual_text = VisualText::new(&mut layout, font, area, size, self.window_size);
+        let (glyphs, lines, vertical) = layout_area(
+            &mut layout,
+            font,
+            area,
+            size,
+            &visual_text,
+            self.window_size,
+        );
+        // Glyph ranges and vertical extents of the lines, columns span their glyphs
+        let ranges: Vec<(Range<usize>, Option<[f32; 2]>)> = match &vertical {
+            Some(vertical) => vertical
+                .columns
+                .iter()
+                .map(|column| (column.clone(), None))
+                .collect(),
+            None => lines
+                .iter()
+                .map(|line| {
+                    let extent = [
+                        line.baseline_y - line.max_ascent,
+                        line.baseline_y - line.min_descent,
+                    ];
+                    (line.glyph_start..line.glyph_end + 1, Some(extent))
+                })
+                .collect(),
+        };
+        let boxes = ranges
+            .into_iter()
+            .enumerate()
+            .filter(|(_, (range, _))| !range.is_empty())
+            .map(|(line, (range, extent))| {
+                let (mut x0, mut y0) = (f32::MAX, f32::MAX);
+                let (mut x1, mut y1) = (f32::MIN, f32::MIN);
+                let (mut start, mut end) = (usize::MAX, 0);
+                for glyph in &glyphs[range.clone()] {
+                    let metrics = font.metrics_indexed(glyph.key.glyph_index, size);
+                    let pen_x = glyph.x - metrics.xmin as f32;
+                    x0 = x0.min(pen_x);
+                    x1 = x1.max(pen_x + metrics.advance_width);
+                    y0 = y0.min(glyph.y);
+                    y1 = y1.max(glyph.y + glyph.height as f32);
+                    let offset = visual_text.logical_glyph(glyph.byte_offset).offset;
+                    let char_len = area.text[offset..].chars().next().map_or(0, char::len_utf8);
+                    start = start.min(offset);
+                    end = end.max(offset + char_len);
+                }
+                if let Some([top, bottom]) = extent {
+                    [y0, y1] = [top, bottom];
+                }
+                let bbox = LineBBox {
+                    line,
+                    x: x0 + area.left_offset,
+                    y: y0 + area.top_offset,
+                    width: x1 - x0,
+                    height: y1 - y0,
+                    char_range: start..end,
+                };
+                (range, bbox)
+            })
+            .collect();
+        Some(boxes)
+    }
+
+    // Glyph positions of a text area as `glyph_positions` returns them, laid out again from the
+    // current text if the area changed since the last `render` call
+    fn current_glyphs(&self, handle: TextAreaHandle) -> Option<Cow<'_, [GlyphInfo<F>]>> {
+        let entry = self.text_areas.get(&handle)?;
+        if !self.is_dirty(handle) {
+            return Some(Cow::Borrowed(&entry.glyphs));
+        }
+        let area = &entry.area;
+        let size = entry.font_size();
+        let (_, font) = resolve_font(&self.fonts, area)?;
+        let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
+        let visual_text = VisualText::new(&mut layout, font, area, size, self.window_size);
+        let (glyphs, lines, vertical) = layout_area(
+            &mut layout,
+            font,
+            area,
+            size,
+            &visual_text,
+            self.window_size,
+        );
+        let logical_glyphs: Vec<LogicalGlyph> = glyphs
+            .iter()
+            .map(|glyph| visual_text.logical_glyph(glyph.byte_offset))
+            .collect();
+        Some(Cow::Owned(glyph_infos(
+            area,
+            size,
+            &glyphs,
+            &logical_glyphs,
+            &line_ranges(&lines, vertical.as_ref()),
+            &char_indices(&area.text),
+        )))
+    }
+
+    /// Returns the number of lines (columns for vertical text) of a text area, counting the
+    /// empty line after a trailing line break. Empty text has one line. Uses the current text,
+    /// even if it changed since the last `render` call.
+    pub fn line_count(&self, handle: TextAreaHandle) -> Option<usize> {
+        let glyphs = self.current_glyphs(handle)?;
+        Some(count_lines(
+            &glyphs,
+            &self.text_areas.get(&handle)?.area.text,
+        ))
+    }
+
+    /// Returns the byte offset of the cursor position on `line` (0-based) closest to `x` in
+    /// pixels, e.g. to move the cursor up or down a line. Uses the current text, even if it
+    /// changed since the last `render` call. The last position of a line is in front of its last glyph (usually the space it
+    /// was wrapped at, or the line break), as the position behind it is the start of the next
+    /// line. Returns `None` if there is no such line.
+    pub fn nearest_char_on_line(
+        &self,
+        handle: TextAreaHandle,
+        line: usize,
+        x: f32,
+    ) -> Option<usize> {
+        let text = &self.text_areas.get(&handle)?.area.text;
+        let glyphs = self.current_glyphs(handle)?;
+        let nearest = line_carets(&glyphs, text, line, false)?
+            .into_iter()
+            .min_by(|a, b| (a.0 - x).abs().total_cmp(&(b.0 - x).abs()));
+        match nearest {
+            Some((_, offset, _)) => Some(offset),
+            // The empty line after a trailing line break, or empty text
+            None => Some(text.len()),
+        }
+    }
+
+    /// Returns the caret position closest to a click at (`click_x`, `click_y`) in pixels, in the
+    /// same space as `glyph_positions` (before `TextArea::transform`), e.g. to start a selection.
+    /// Uses the current text, even if it changed since the last `render` call. Clicks above or
+    /// below the text go to the nearest line. Behind the last glyph of a wrapped line, the caret
+    /// is at the start of the next line with `Affinity::Upstream`, as it is shown at the end of
+    /// the clicked line.
+    /// Returns `None` if the area doesn't exist or uses `vertical_layout`.
+    pub fn caret_from_click(
+        &self,
+        handle: TextAreaHandle,
+        click_x: f32,
+        click_y: f32,
+    ) -> Option<CaretPosition> {
+        let entry = self.text_areas.get(&handle)?;
+        if entry.area.vertical_layout {
+            return None;
+        }
+        let glyphs = self.current_glyphs(handle)?;
+        let line_count = count_lines(&glyphs, &entry.area.text);
+        // Top and bottom of the glyphs on every line
+        let mut extents = vec![[f32::MAX, f32::MIN]; line_count];
+        for glyph in glyphs.iter() {
+            let Some([top, bottom]) = extents.get_mut(glyph.line) else {
+                continue;
+            };
+            *top = top.min(glyph.y);
+            *bottom = bottom.max(glyph.y + glyph.height);
+        }
+        // The empty line after a trailing line break continues the spacing of the lines above
+        if let [.., previous, last] = extents.as_mut_slice() {
+            if last[0] > last[1] && previous[0] <= previous[1] {
+                let pitch = (previous[1] - previous[0]).max(entry.font_size());
+                *last = [previous[0] + pitch, previous[1] + pitch];
+            }
+        }
+        let distance = |[top, bottom]: [f32; 2]| (top - click_y).max(click_y - bottom).max(0.0);
+        let line = (0..line_count)
+            .filter(|&line| extents[line][0] <= extents[line][1])
+            .min_by(|&a, &b| distance(extents[a]).total_cmp(&distance(extents[b])))
+            .unwrap_or(0);
+        // Zero width glyphs like the space a line was wrapped at have two carets at the same x,
+        // the one behind them wins
+        let nearest = line_carets(&glyphs, &entry.area.text, line, true)?
+            .into_iter()
+            .rev()
+            .min_by(|a, b| (a.0 - click_x).abs().total_cmp(&(b.0 - click_x).abs()));
+        let (byte_offset, affinity) = match nearest {
+            Some((_, offset, affinity)) => (offset, affinity),
+            None => (entry.area.text.len(), Affinity::Downstream),
+        };
+        Some(CaretPosition {
+            byte_offset,
+            line,
+            affinity,
+        })
+    }
+
+    /// Returns the byte ranges of all words in the text of a text area, split by the Unicode
+    /// word boundary rules. Whitespace and punctuation between the words is skipped.
+    #[cfg(feature = "text-editing")]
+    pub fn word_ranges(&self, handle: TextAreaHandle) -> Option<Vec<Range<usize>>> {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        let entry = self.text_areas.get(&handle)?;
+        Some(
+            entry
+                .area
+                .text
+                .unicode_word_indices()
+                .map(|(start, word)| start..start + word.len())
+                .collect(),
+        )
+    }
+
+    /// Returns the byte range of the word at `byte_offset`, e.g. to select it on a double click.
+    /// Between words, the run of whitespace or the punctuation at the offset is returned
+    /// instead, like most text editors do. An offset at the end of the text belongs to its
+    /// last segment. Returns `None` if the offset is past the end of the text.
+    #[cfg(feature = "text-editing")]
+    pub fn word_at_offset(
+        &self,
+        handle: TextAreaHandle,
+        byte_offset: usize,
+    ) -> Option<Range<usize>> {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        let text = &self.text_areas.get(&handle)?.area.text;
+        if byte_offset > text.len() {
+            return None;
+        }
+        let mut last = None;
+        for (start, segment) in text.split_word_bound_indices() {
+            let range = start..start + segment.len();
+            if range.contains(&byte_offset) {
+                return Some(range);
+            }
+            last = Some(range);
+        }
+        // The end of the text, or empty text
+        Some(last.unwrap_or(0..0))
+    }
+
+    /// Returns the byte range of the grapheme cluster (what users perceive as one character,
+    /// e.g. an emoji made of several code points) at `byte_offset`. Returns `None` if the offset
+    /// is at or past the end of the text.
+    #[cfg(feature = "text-editing")]
+    pub fn grapheme_at_offset(
+        &self,
+        handle: TextAreaHandle,
+        byte_offset: usize,
+    ) -> Option<Range<usize>> {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        let text = &self.text_areas.get(&handle)?.area.text;
+        text.grapheme_indices(true)
+            .map(|(start, grapheme)| start..start + grapheme.len())
+            .find(|range| range.contains(&byte_offset))
+    }
+
+    /// Returns the byte offset of the cursor position one grapheme cluster after `byte_offset`,
+    /// or the end of the text if the offset is already there. Returns `None` if the offset is
+    /// past the end of the text.
+    #[cfg(feature = "text-editing")]
+    pub fn next_grapheme(&self, handle: TextAreaHandle, byte_offset: usize) -> Option<usize> {
+        let text = &self.text_areas.get(&handle)?.area.text;
+        if byte_offset > text.len() {
+            return None;
+        }
+        Some(
+            self.grapheme_at_offset(handle, byte_offset)
+                .map_or(text.len(), |grapheme| grapheme.end),
+        )
+    }
+
+    /// Returns the byte offset of the cursor position one grapheme cluster before `byte_offset`,
+    /// or 0 if the offset is already at the start of the text. Returns `None` if the offset is
+    /// past the end of the text.
+    #[cfg(feature = "text-editing")]
+    pub fn prev_grapheme(&self, handle: TextAreaHandle, byte_offset: usize) -> Option<usize> {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        let text = &self.text_areas.get(&handle)?.area.text;
+        if byte_offset > text.len() {
+            return None;
+        }
+        Some(
+            text.grapheme_indices(true)
+                .map(|(start, _)| start)
+                .take_while(|&start| start < byte_offset)
+                .last()
+                .unwrap_or(0),
+        )
+    }
+
+    /// Returns the byte ranges of all non-overlapping matches of `query` in the text of a text area.
+    pub fn find_text(&self, handle: TextAreaHandle, query: &str) -> Vec<Range<usize>> {
+        let Some(entry) = self.text_areas.get(&handle) else {
+            return Vec::new();
+        };
+        if query.is_empty() {
+            return Vec::new();
+        }
+        entry
+            .area
+            .text
+            .match_indices(query)
+            .map(|(start, _)| start..start + query.len())
+            .collect()
+    }
+
+    /// Returns the bounds of all matches of `query` in the text of a text area, one range per
+    /// line a match is on. Uses the current text, even if it changed since the last `render`
+    /// call.
+    pub fn find_text_positions(
+        &self,
+        handle: TextAreaHandle,
+        query: &str,
+    ) -> Vec<GlyphPositionRange> {
+        let (Some(entry), Some(glyphs)) =
+            (self.text_areas.get(&handle), self.current_glyphs(handle))
+        else {
+            return Vec::new();
+        };
+        let text = &entry.area.text;
+        let mut ranges: Vec<GlyphPositionRange> = Vec::new();
+        for byte_range in self.find_text(handle, query) {
+            let start = text[..byte_range.start].chars().count();
+            let chars = start..start + text[byte_range].chars().count();
+            let first = ranges.len();
+            for glyph in glyphs
+                .iter()
+                .filter(|glyph| chars.contains(&glyph.char_index))
+            {
+                let [end_x, end_y] = [glyph.x + glyph.width, glyph.y + glyph.height];
+                match ranges[first..]
+                    .iter_mut()
+                    .find(|range| range.line == glyph.line)
+                {
+                    Some(range) => {
+                        range.start_x = range.start_x.min(glyph.x);
+                        range.start_y = range.start_y.min(glyph.y);
+                        range.end_x = range.end_x.max(end_x);
+                        range.end_y = range.end_y.max(end_y);
+                    }
+                    None => ranges.push(GlyphPositionRange {
+                        start_x: glyph.x,
+                        start_y: glyph.y,
+                        end_x,
+                        end_y,
+                        line: glyph.line,
+                    }),
+                }
+            }
+        }
+        ranges
+    }
+
+    /// Returns the rectangle (`[x, y, width, height]` in pixels, before `TextArea::transform`)
+    /// of the cursor of a text area in front of the char at `byte_offset` into its text, as it
+    /// would be drawn with `TextArea::cursor_position`. Offsets at the end of a line or of the
+    /// text are behind the last glyph. Returns `None` if the area or its font don't exist.
+    pub fn cursor_rect(&self, handle: TextAreaHandle, byte_offset: usize) -> Option<[f32; 4]> {
+        let entry = self.text_areas.get(&handle)?;
+        let area = &entry.area;
+        let size = entry.font_size();
+        let (_, font) = resolve_font(&self.fonts, area)?;
+        let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
+        let visual_text = VisualText::new(&mut layout, font, area, size, self.window_size);
+        let (glyphs, lines, vertical) = layout_area(
+            &mut layout,
+            font,
+            area,
+            size,
+            &visual_text,
+            self.window_size,
+        );
+        let logical_glyphs: Vec<LogicalGlyph> = glyphs
+            .iter()
+            .map(|glyph| visual_text.logical_glyph(glyph.byte_offset))
+            .collect();
+        let [area_x, area_y, _, _] = area.pixel_rect(self.window_size);
+        let [x0, y0, x1, y1] = match &vertical {
+            Some(vertical) => vertical.caret_rect(&logical_glyphs, byte_offset, area.cursor_width),
+            None => {
+                let [x, top, bottom] = caret_position(
+                    &glyphs,
+                    &lines,
+                    &logical_glyphs,
+                    font,
+                    size,
+                    byte_offset,
+                    [area_x, area_y],
+                );
+                [x, top, x + area.cursor_width, bottom]
+            }
+        };
+        Some([
+            x0 + area.left_offset,
+            y0 + area.top_offset,
+            x1 - x0,
+            y1 - y0,
+        ])
+    }
+
+    /// Returns the height of the laid out text of a text area in pixels, which can be larger
+    /// than the area itself. Use it together with `TextArea::scroll_y` for scrolling.
+    pub fn text_area_content_height(&self, handle: TextAreaHandle) -> Option<f32> {
+        let [_, content_height] = self.area_content_size(handle)?;
+        Some(content_height)
+    }
+
+    /// Returns whether the laid out text of a text area is wider or taller than the area,
+    /// e.g. to show a scrollbar. See `overflow_amount`.
+    pub fn is_text_overflowing(&self, handle: TextAreaHandle) -> Option<bool> {
+        let [x, y] = self.overflow_amount(handle)?;
+        Some(x > 0.0 || y > 0.0)
+    }
+
+    /// Returns how many pixels the laid out text of a text area extends past its width and
+    /// height, 0 if it fits. The text is wrapped at the width of the area, so usually only the
+    /// height overflows.
+    pub fn overflow_amount(&self, handle: TextAreaHandle) -> Option<[f32; 2]> {
+        let [content_width, content_height] = self.area_content_size(handle)?;
+        let area = &self.text_areas.get(&handle)?.area;
+        let [_, _, width, height] = area.pixel_rect(self.window_size);
+        Some([
+            (content_width - width).max(0.0),
+            (content_height - height).max(0.0),
+        ])
+    }
+
+    // Size of the text of an area laid out at its width with unbounded height
+    fn area_content_size(&self, handle: TextAreaHandle) -> Option<[f32; 2]> {
+        let entry = self.text_areas.get(&handle)?;
+        let area = &entry.area;
+        let size = entry.font_size();
+        let (_, font) = resolve_font(&self.fonts, area)?;
+        let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
+        let visual_text = VisualText::new(&mut layout, font, area, size, self.window_size);
+        let [_, _, width, _] = area.pixel_rect(self.window_size);
+        Some(content_size(
+            &mut layout,
+            font,
+            area,
+            size,
+            visual_text.text(),
+            Some(width),
+            None,
+        ))
+    }
+
+    pub fn remove_text_area(&mut self, handle: TextAreaHandle) {
+        self.debug_show_area_borders_lines = None;
+        self.area_borders = None;
+        self.debug_show_glyph_bounds_lines = None;
+        self.instance_groups.mark_dirty();
+        if self.text_areas.remove(&handle).is_some() {
+            self.free_handle(handle);
+        }
+        self.text_area_names
+            .retain(|_, named_handle| *named_handle != handle);
+    }
+
+    /// Shows or hides all text areas in `layer`.
+    pub fn set_layer_visible(&mut self, layer: u32, visible: bool) {
+        let handles: Vec<_> = self
+            .text_areas
+            .iter_mut()
+            .filter(|(_, entry)| entry.area.layer == layer)
+            .map(|(&handle, entry)| {
+                entry.area.visible = visible;
+                handle
+            })
+            .collect();
+        for handle in handles {
+            self.insert_dirty(handle);
+        }
+        self.instance_groups.mark_dirty();
+        self.debug_show_area_borders_lines = None;
+        self.area_borders = None;
+    }
+
+    /// Removes all text areas in `layer`.
+    pub fn remove_layer(&mut self, layer: u32) {
+        let handles: Vec<_> = self
+            .text_areas
+            .iter()
+            .filter(|(_, entry)| entry.area.layer == layer)
+            .map(|(&handle, _)| handle)
+            .collect();
+        for handle in handles {
+            self.remove_text_area(handle);
+        }
+    }
+
+    /// Removes all text areas and frees their buffers.
+    pub fn clear_text_areas(&mut self) {
+        let handles: Vec<_> = self.text_areas.drain().map(|(handle, _)| handle).collect();
+        for handle in handles {
+            self.free_handle(handle);
+        }
+        self.text_area_names.clear();
+        self.dirty_text_areas.clear();
+        self.debug_show_area_borders_lines = None;
+        self.area_borders = None;
+        self.debug_show_glyph_bounds_lines = None;
+        self.instance_groups.mark_dirty();
+    }
+
+    /// Returns mutable access to a text area. It is only laid out again on the next `render`
+    /// once it was actually accessed mutably, see `TextAreaGuard`.
+    pub fn text_area_mut(&mut self, handle: TextAreaHandle) -> Option<TextAreaGuard<'_, F, V>> {
+        if !self.text_areas.contains_key(&handle) {
+            return None;
+        }
+        Some(TextAreaGuard {
+            easy_text: self,
+            handle,
+        })
+    }
+
+    /// Lays out and uploads a text area again on the next `render`.
+    pub fn mark_dirty(&mut self, handle: TextAreaHandle) {
+        if !self.text_areas.contains_key(&handle) {
+            return;
+        }
+        self.insert_dirty(handle);
+        self.debug_show_area_borders_lines = None;
+        self.area_borders = None;
+    }
+
+    /// Highlights all matches of the regular expression `pattern` in the text of a text area
+    /// with `color`, in addition to its existing `TextArea::highlights`.
+    #[cfg(feature = "regex")]
+    pub fn set_highlight_pattern(
+        &mut self,
+        handle: TextAreaHandle,
+        pattern: &str,
+        color: [f32; 4],
+    ) -> Result<(), EasyTextError> {
+        let regex = regex::Regex::new(pattern)?;
+        if let Some(mut area) = self.text_area_mut(handle) {
+            let matches: Vec<_> = regex
+                .find_iter(&area.text)
+                .filter(|found| !found.is_empty())
+                .map(|found| (found.range(), color))
+                .collect();
+            area.highlights.extend(matches);
+        }
+        Ok(())
+    }
+
+    /// Reveals `count` more chars of a text area with a `TextArea::typewriter`. Returns `true`
+    /// once all chars are visible, or if the text area has no typewriter.
+    pub fn advance_typewriter(&mut self, handle: TextAreaHandle, count: usize) -> bool {
+        let Some(mut area) = self.text_area_mut(handle) else {
+            return true;
+        };
+        let Some(typewriter) = &mut area.typewriter else {
+            return true;
+        };
+        typewriter.chars_visible = (typewriter.chars_visible + count).min(typewriter.total_chars);
+        typewriter.chars_visible == typewriter.total_chars
+    }
+
+    /// Shows the text an input method editor is composing at the cursor of a text area,
+    /// or stops showing it if `composition` is `None`.
+    pub fn set_ime_composition(
+        &mut self,
+        handle: TextAreaHandle,
+        composition: Option<ImeComposition>,
+    ) {
+        if let Some(mut area) = self.text_area_mut(handle) {
+            area.ime_composition = composition;
+        }
+    }
+
+    pub fn text_area(&self, handle: TextAreaHandle) -> Option<&TextArea<F>> {
+        self.text_areas.get(&handle).map(|entry| &entry.area)
+    }
+
+    /// Returns the handles of all text areas, in no particular order.
+    pub fn text_area_handles(&self) -> impl Iterator<Item = TextAreaHandle> + '_ {
+        self.text_areas.keys().copied()
+    }
+
+    pub fn text_area_count(&self) -> usize {
+        self.text_areas.len()
+    }
+
+    /// Returns the number of text areas that will be laid out again on the next `render`.
+    pub fn pending_dirty_count(&self) -> usize {
+        self.dirty_text_areas.len()
+    }
+
+    /// Returns true if the text area will be laid out again on the next `render`.
+    pub fn is_dirty(&self, handle: TextAreaHandle) -> bool {
+        self.dirty_text_areas.binary_search(&handle).is_ok()
+    }
+
+    /// Returns the text of a text area, e.g. for copying it to the clipboard.
+    pub fn export_plain_text(&self, handle: TextAreaHandle) -> Option<String> {
+        self.text_area(handle).map(|area| area.text.clone())
+    }
+
+    /// Returns the text of every text area, sorted by handle.
+    pub fn export_all_plain_text(&self) -> Vec<(TextAreaHandle, String)> {
+        let mut texts: Vec<_> = self
+            .text_areas
+            .iter()
+            .map(|(handle, entry)| (*handle, entry.area.text.clone()))
+            .collect();
+        texts.sort_unstable_by_key(|(handle, _)| *handle);
+        texts
+    }
+
+    /// Advances the cursor blink timer, the cursors of all text areas are shown and hidden
+    /// every `CURSOR_BLINK_INTERVAL` seconds. Call this once per frame with the time since the last call.
+    pub fn update_cursor_blink(&mut self, elapsed_seconds: f64) {
+        self.cursor_blink_timer += elapsed_seconds;
+        if self.cursor_blink_timer < CURSOR_BLINK_INTERVAL {
+            return;
+        }
+        self.cursor_blink_timer %= CURSOR_BLINK_INTERVAL;
+        self.cursor_blink_visible = !self.cursor_blink_visible;
+        let with_cursor: Vec<_> = self
+            .text_areas
+            .iter()
+            .filter(|(_, entry)| entry.area.cursor_position.is_some())
+            .map(|(&handle, _)| handle)
+            .collect();
+        for handle in with_cursor {
+            self.insert_dirty(handle);
+        }
+    }
+
+    /// Advances the time of all text area animations (e.g. `TextArea::wave_animation`).
+    /// Call this once per frame with the time since the last call.
+    pub fn tick_animations(&mut self, elapsed_seconds: f64) {
+        self.animation_time += elapsed_seconds;
+        let mut animated = Vec::new();
+        let mut faded = Vec::new();
+        for (&handle, entry) in &mut self.text_areas {
+            let mut changed = entry.area.wave_animation.is_some();
+            if let Some(fade) = &entry.fade {
+                let (opacity, finished) = fade.opacity(self.animation_time);
+                entry.area.opacity = opacity;
+                changed = true;
+                if finished {
+                    entry.fade = None;
+                    faded.push(handle);
+                }
+            }
+            if let Some(animation) = &entry.color_animation {
+                match animation.color(self.animation_time) {
+                    Some((color, finished)) => {
+                        entry.area.color = color;
+                        changed = true;
+                        if finished {
+                            entry.color_animation = None;
+                        }
+                    }
+                    None => entry.color_animation = None,
+                }
+            }
+            if changed {
+                animated.push(handle);
+            }
+        }
+        for handle in animated {
+            self.insert_dirty(handle);
+        }
+        if let Some(on_fade_complete) = self.on_fade_complete {
+            faded.sort_unstable();
+            faded.into_iter().for_each(on_fade_complete);
+        }
+    }
+
+    /// Calls `tick_animations` with the time since the last call, with `now_secs` being the
+    /// current time in seconds from any fixed starting point. The first call only stores the time.
+    pub fn tick(&mut self, now_secs: f64) {
+        let elapsed = self
+            .last_tick_time
+            .map_or(0.0, |last_tick_time| now_secs - last_tick_time);
+        self.last_tick_time = Some(now_secs);
+        self.tick_animations(elapsed.max(0.0));
+    }
+
+    /// Returns true if any text area has a running fade or color animation, or a wave.
+    pub fn is_animating(&self) -> bool {
+        self.text_areas.values().any(|entry| {
+            entry.fade.is_some()
+                || entry.color_animation.is_some()
+                || entry.area.wave_animation.is_some()
+        })
+    }
+
+    /// Fades `TextArea::opacity` of a text area from its current value to `target_alpha`
+    /// over `duration_secs` seconds of `tick_animations`, replacing any running fade.
+    pub fn animate_fade(&mut self, handle: TextAreaHandle, target_alpha: f32, duration_secs: f32) {
+        let animation_time = self.animation_time;
+        if let Some(entry) = self.text_areas.get_mut(&handle) {
+            entry.fade = Some(FadeAnimation {
+                start_alpha: entry.area.opacity,
+                target_alpha,
+                start_time: animation_time,
+                duration: duration_secs as f64,
+            });
+        }
+    }
+
+    /// Animates `TextArea::color` of a text area through `keyframes`, starting at the next
+    /// `tick_animations`. Replaces any running color animation.
+    pub fn animate_color(&mut self, handle: TextAreaHandle, mut keyframes: Vec<ColorKeyframe>) {
+        keyframes.sort_by(|a, b| a.time_secs.total_cmp(&b.time_secs));
+        let start_time = self.animation_time;
+        if let Some(entry) = self.text_areas.get_mut(&handle) {
+            entry.color_animation = Some(ColorAnimation {
+                keyframes,
+                start_time,
+            });
+        }
+    }
+
+    /// Sets a function that is called from `tick_animations` with every text area whose fade finished.
+    pub fn set_on_fade_complete(&mut self, on_fade_complete: Option<fn(TextAreaHandle)>) {
+        self.on_fade_complete = on_fade_complete;
+    }
+
+    /// Sets a comparator that decides the order text areas are drawn in, earlier areas are
+    /// drawn below later ones. Without one, the order is unspecified.
+    pub fn set_draw_order_fn(&mut self, draw_order: Option<DrawOrderFn<F>>) {
+        self.draw_order = draw_order;
+    }
+
+    fn insert_dirty(&mut self, handle: TextAreaHandle) {
+        self.instance_groups.mark_dirty();
+        if let Err(index) = self.dirty_text_areas.binary_search(&handle) {
+            self.dirty_text_areas.insert(index, handle);
+        }
+    }
+
+    /// Creates the pipelines for rendering into a color target of `target_format`, e.g. an
+    /// off-screen texture next to the main window. The atlas and text areas stay shared.
+    pub fn add_render_target(
+        &mut self,
+        device: &Device,
+        target_format: TextureFormat,
+    ) -> RenderTargetHandle {
+        let mut pipelines = Pipelines::new::<V>(device, &self.pipeline_sources, target_format);
+        for material in &self.materials {
+            pipelines.add_material::<V>(device, &self.pipeline_sources, material);
+        }
+        self.render_targets.push(pipelines);
+        RenderTargetHandle {
+            id: self.render_targets.len() as u32 - 1,
+        }
+    }
+
+    /// Creates the pipelines for `material`, for all render targets. Text areas use it
+    /// with `TextArea::material`, which takes precedence over `TextArea::blend_mode`.
+    pub fn register_material(&mut self, device: &Device, material: Material) -> MaterialHandle {
+        let sources = &self.pipeline_sources;
+        for pipelines in std::iter::once(&mut self.pipelines).chain(&mut self.render_targets) {
+            pipelines.add_material::<V>(device, sources, &material);
+        }
+        self.materials.push(material);
+        MaterialHandle {
+            id: self.materials.len() as u32 - 1,
+        }
+    }
+
+    /// Prepares and draws all text areas, see `prepare` and `draw` to do both separately.
+    pub fn render(&mut self, device: &Device, queue: &Queue, render_pass: &mut RenderPass) {
+        self.prepare(device, queue);
+        self.draw(render_pass);
+    }
+
+    /// Like `render`, but into a color target of the format of `target`.
+    pub fn render_to_target(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        render_pass: &mut RenderPass,
+        target: RenderTargetHandle,
+    ) {
+        self.prepare(device, queue);
+        self.draw_to_target(render_pass, target);
+    }
+
+    /// Lays out all text areas that changed since the last call, rasterizes their new glyphs
+    /// into the atlas and uploads their vertices. Doesn't need a render pass, so the CPU work
+    /// can be done before or while the GPU is busy with other passes. Followed by `draw`.
+    pub fn prepare(&mut self, device: &Device, queue: &Queue) {
+        let mut stats = RenderStats::default();
+        for handle in self.dirty_text_areas.drain(..) {
+            let entry = match self.text_areas.get_mut(&handle) {
+                Some(entry) => entry,
+                None => continue,
+            };
+            stats.dirty_areas_processed += 1;
+            let (face, font) = resolve_font(&self.fonts, &entry.area).expect("Font not found");
+            entry.font_face = face;
+            // The rects of the area (caret, selection, ...) are drawn with the bind group of
+            // its atlas page, which only exists once a glyph was inserted with `per_font_atlas`
+            self.atlas.ensure_page(device, (entry.area.font, face));
+            shrink_to_fit(&mut self.layout, font, entry, self.window_size);
+            let size = entry.font_size();
+            let visual_text =
+                VisualText::new(&mut self.layout, font, &entry.area, size, self.window_size);
+            if entry.area.auto_width || entry.area.auto_height {
+                let area = &mut entry.area;
+                let [_, _, width, height] = area.pixel_rect(self.window_size);
+                let [content_width, content_height] = content_size(
+                    &mut self.layout,
+                    font,
+                    area,
+                    size,
+                    visual_text.text(),
+                    (!area.auto_width).then_some(width),
+                    (!area.auto_height).then_some(height),
+                );
+                area.set_pixel_size(
+                    area.auto_width.then_some(content_width),
+                    area.auto_height.then_some(content_height),
+                    self.window_size,
+                );
+            }
+            let area = &entry.area;
+            let [area_x, area_y, area_width, area_height] = area.pixel_rect(self.window_size);
+            let area_info = AreaInfo {
+                transform: area.transform.map_or(IDENTITY, |transform| {
+                    let [origin_x, origin_y] = area.transform_origin;
+                    let pivot = [
+                        area_x + area_width * origin_x,
+                        area_y + area_height * origin_y,
+                    ];
+                    transform_around(transform, pivot)
+                }),
+                clip_rect: [area_x, area_y, area_x + area_width, area_y + area_height],
+                corner_radius: area
+                    .corner_radius
+                    .clamp(0.0, area_width.min(area_height) / 2.0),
+                _padding: [0.0; 3],
+            };
+            match &entry.area_info_buffer {
+                Some(buffer) => queue.write_buffer(buffer, 0, bytemuck::cast_slice(&[area_info])),
+                None => {
+                    let buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                        label: Some("EasyText Area Info Uniform Buffer"),
+                        contents: bytemuck::cast_slice(&[area_info]),
+                        usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    });
+                    entry.area_info_bind_group =
+                        Some(device.create_bind_group(&wgpu::BindGroupDescriptor {
+                            layout: &self.area_info_bind_group_layout,
+                            entries: &[wgpu::BindGroupEntry {
+                                binding: 0,
+                                resource: buffer.as_entire_binding(),
+                            }],
+                            label: Some("EasyText Area Info Bind Group"),
+                        }));
+                    entry.area_info_buffer = Some(buffer);
+                }
+            }
+            let (laid_out_glyphs, lines, vertical) = layout_area(
+                &mut self.layout,
+                font,
+                area,
+                size,
+                &visual_text,
+                self.window_size,
+            );
+            let mut vertices = Vec::new();
+            let mut shadow_vertices = Vec::new();
+            let mut outline_vertices = Vec::new();
+            let indexed = self.quad_indices.is_some();
+            // Distance fields are independent of the size, so all sizes share one atlas entry
+            let raster_size = self.atlas.quantize_size(size);
+            let size_key = if cfg!(feature = "sdf") {
+                0
+            } else {
+                raster_size as u16
+            };
+            let mut glyph_rects = Vec::new();
+            let preedit = visual_text.preedit();
+            let logical_glyphs: Vec<LogicalGlyph> = laid_out_glyphs
+                .iter()
+                .map(|glyph| visual_text.logical_glyph(glyph.byte_offset))
+                .collect();
+            let char_indices = char_indices(&area.text);
+            let glyphs = glyph_infos(
+                area,
+                size,
+                &laid_out_glyphs,
+                &logical_glyphs,
+                &line_ranges(&lines, vertical.as_ref()),
+                &char_indices,
+            );
+            for (i, (glyph, logical)) in laid_out_glyphs.iter().zip(&logical_glyphs).enumerate() {
+                // Whitespace and control characters
+                if glyph.width == 0 || glyph.height == 0 {
+                    continue;
+                }
+                if area.typewriter.is_some_and(|typewriter| {
+                    char_indices[logical.offset] >= typewriter.chars_visible
+                }) {
+                    continue;
+                }
+                let prepared_glyph =
+                    match self
+                        .atlas
+                        .get((area.font, face), size_key, glyph.key.glyph_index)
+                    {
+                        Some(glyph) => glyph,
+                        None => {
+                            #[cfg(not(feature = "sdf"))]
+                            let (metrics, bitmap) = match self.atlas.subpixel() {
+                                true => {
+                                    rasterize_subpixel(font, glyph.key.glyph_index, raster_size)
+                                }
+                                false => font.rasterize_indexed(glyph.key.glyph_index, raster_size),
+                            };
+                            #[cfg(feature = "sdf")]
+                            let (metrics, bitmap) =
+                                sdf::rasterize_sdf(font, glyph.key.glyph_index, &self.sdf_settings);
+                            stats.atlas_uploads += 1;
+                            self.atlas.insert(
                                 device,
                                 queue,
-                                area.font,
-                                area.size as u16,
+                                (area.font, face),
+                                size_key,
                                 glyph.key.glyph_index,
                                 metrics,
                                 bitmap,
@@ -423,163 +2221,1375 @@ impl<F: Eq + Hash + Copy> EasyText<F> {
                         }
                     };
                 // Skip glyphs outside of the text area
-                if glyph.y + (glyph.height as f32) < area.y || glyph.y > area.y + area.height {
+                if glyph.y + (glyph.height as f32) < area_y || glyph.y > area_y + area_height {
                     continue;
                 }
-                if glyph.x + (glyph.width as f32) < area.x || glyph.x > area.x + area.width {
+                if glyph.x + (glyph.width as f32) < area_x || glyph.x > area_x + area_width {
                     continue;
                 }
                 let allocation = match prepared_glyph.allocation {
                     Some(allocation) => allocation.rectangle,
                     None => continue,
                 };
-                let atlas_size = self.atlas.size as f32;
-                vertices.extend_from_slice(&[
-                    GlyphVertex {
-                        pos: [glyph.x + area.left_offset, glyph.y + area.top_offset],
-                        tex_coord: [
-                            allocation.min.x as f32 / atlas_size,
-                            allocation.min.y as f32 / atlas_size,
-                        ],
-                    },
-                    GlyphVertex {
-                        pos: [
-                            glyph.x + glyph.width as f32 + area.left_offset,
-                            glyph.y + area.top_offset,
-                        ],
-                        tex_coord: [
-                            (allocation.min.x as usize + glyph.width) as f32 / atlas_size,
-                            allocation.min.y as f32 / atlas_size,
-                        ],
-                    },
-                    GlyphVertex {
-                        pos: [
-                            glyph.x + glyph.width as f32 + area.left_offset,
-                            glyph.y + glyph.height as f32 + area.top_offset,
-                        ],
-                        tex_coord: [
-                            (allocation.min.x as usize + glyph.width) as f32 / atlas_size,
-                            (allocation.min.y as usize + glyph.height) as f32 / atlas_size,
-                        ],
-                    },
-                    GlyphVertex {
-                        pos: [glyph.x + area.left_offset, glyph.y + area.top_offset],
-                        tex_coord: [
-                            allocation.min.x as f32 / atlas_size,
-                            allocation.min.y as f32 / atlas_size,
-                        ],
-                    },
-                    GlyphVertex {
-                        pos: [
-                            glyph.x + glyph.width as f32 + area.left_offset,
-                            glyph.y + glyph.height as f32 + area.top_offset,
+                // Size of the glyph in the atlas, not on screen
+                let [atlas_width, atlas_height] = [
+                    prepared_glyph.metrics.width as f32,
+                    prepared_glyph.metrics.height as f32,
+                ];
+                let atlas_size = self.atlas.page_size((area.font, face)) as f32;
+                let wave_offset = area.wave_animation.map_or(0.0, |wave| {
+                    let time = self.animation_time as f32 * wave.speed;
+                    let phase = char_indices[logical.offset] as f32 * wave.phase_offset_per_char;
+                    wave.amplitude * (wave.frequency * time + phase).sin()
+                });
+                let x = glyph.x + area.left_offset;
+                let y = glyph.y + area.top_offset + wave_offset;
+                let padding = self.atlas.glyph_padding() as i32;
+                let u = (allocation.min.x + padding) as f32 / atlas_size;
+                let v = (allocation.min.y + padding) as f32 / atlas_size;
+                #[cfg(not(feature = "sdf"))]
+                let rect = [x, y, x + glyph.width as f32, y + glyph.height as f32];
+                // The distance field is rendered at the base size and padded by the spread
+                #[cfg(feature = "sdf")]
+                let rect = {
+                    let scale = size / sdf::SDF_BASE_SIZE;
+                    let pad = self.sdf_settings.spread.ceil() * scale;
+                    [
+                        x - pad,
+                        y - pad,
+                        x - pad + atlas_width * scale,
+                        y - pad + atlas_height * scale,
+                    ]
+                };
+                let tex_rect = [
+                    u,
+                    v,
+                    u + atlas_width / atlas_size,
+                    v + atlas_height / atlas_size,
+                ];
+                glyph_rects.push(rect);
+                let glyph_info = &glyphs[i];
+                if let Some(shadow) = &area.shadow {
+                    let [x0, y0, x1, y1] = rect;
+                    push_glyph_quad::<F, V>(
+                        &mut shadow_vertices,
+                        indexed,
+                        glyph_info,
+                        [
+                            x0 + shadow.offset_x,
+                            y0 + shadow.offset_y,
+                            x1 + shadow.offset_x,
+                            y1 + shadow.offset_y,
                         ],
-                        tex_coord: [
-                            (allocation.min.x as usize + glyph.width) as f32 / atlas_size,
-                            (allocation.min.y as usize + glyph.height) as f32 / atlas_size,
+                        tex_rect,
+                        shadow.color,
+                        area,
+                    );
+                }
+                if let Some(outline) = &area.outline {
+                    let [x0, y0, x1, y1] = rect;
+                    let w = outline.width;
+                    for [dx, dy] in [[-w, 0.0], [w, 0.0], [0.0, -w], [0.0, w]] {
+                        push_glyph_quad::<F, V>(
+                            &mut outline_vertices,
+                            indexed,
+                            glyph_info,
+                            [x0 + dx, y0 + dy, x1 + dx, y1 + dy],
+                            tex_rect,
+                            outline.color,
+                            area,
+                        );
+                    }
+                }
+                let color = match &preedit {
+                    Some(preedit) if preedit.contains(&glyph.byte_offset) => area.ime_color,
+                    _ => area.color,
+                };
+                push_glyph_quad::<F, V>(
+                    &mut vertices,
+                    indexed,
+                    glyph_info,
+                    rect,
+                    tex_rect,
+                    color,
+                    area,
+                );
+            }
+            // Outlines of all glyphs are drawn first, so they never cover a neighbouring glyph
+            if !outline_vertices.is_empty() {
+                outline_vertices.append(&mut vertices);
+                vertices = outline_vertices;
+            }
+            let mut rect_vertices = Vec::new();
+            // Lines span the area, columns of vertical text its full height
+            for (line, color) in &area.highlighted_lines {
+                let Some(range) = logical_line_range(&area.text, *line) else {
+                    continue;
+                };
+                let rects = match &vertical {
+                    Some(vertical) => {
+                        let columns = match range.is_empty() {
+                            // The empty line after a trailing line break, or empty text
+                            true => vec![vertical.caret_rect(&logical_glyphs, range.start, 0.0)],
+                            false => vertical.range_rects(&logical_glyphs, &range),
+                        };
+                        columns
+                            .into_iter()
+                            .map(|[x0, _, x1, _]| [x0, area_y, x1, area_y + area_height])
+                            .collect()
+                    }
+                    None => {
+                        let extents = match range.is_empty() {
+                            true => {
+                                let [_, top, bottom] = caret_position(
+                                    &laid_out_glyphs,
+                                    &lines,
+                                    &logical_glyphs,
+                                    font,
+                                    size,
+                                    range.start,
+                                    [area_x, area_y],
+                                );
+                                vec![[top, bottom]]
+                            }
+                            false => line_extents(&lines, &logical_glyphs, &range),
+                        };
+                        extents
+                            .into_iter()
+                            .map(|[top, bottom]| [area_x, top, area_x + area_width, bottom])
+                            .collect::<Vec<_>>()
+                    }
+                };
+                for [x0, y0, x1, y1] in rects {
+                    rect_vertices.extend_from_slice(&RectVertex::quad(
+                        [
+                            x0 + area.left_offset,
+                            y0 + area.top_offset,
+                            x1 + area.left_offset,
+                            y1 + area.top_offset,
                         ],
-                    },
-                    GlyphVertex {
-                        pos: [
-                            glyph.x + area.left_offset,
-                            glyph.y + glyph.height as f32 + area.top_offset,
+                        area.z_depth,
+                        *color,
+                    ));
+                }
+            }
+            // Highlights first, so the selection is drawn on top of them
+            let highlighted = area
+                .highlights
+                .iter()
+                .map(|(range, color)| (range, *color))
+                .chain(
+                    area.selection
+                        .iter()
+                        .map(|range| (range, area.selection_color)),
+                );
+            for (range, color) in highlighted {
+                let rects = match &vertical {
+                    Some(vertical) => vertical.range_rects(&logical_glyphs, range),
+                    None => {
+                        range_rects(&laid_out_glyphs, &lines, &logical_glyphs, font, size, range)
+                    }
+                };
+                for rect in rects {
+                    let [x0, y0, x1, y1] = rect;
+                    rect_vertices.extend_from_slice(&RectVertex::quad(
+                        [
+                            x0 + area.left_offset,
+                            y0 + area.top_offset,
+                            x1 + area.left_offset,
+                            y1 + area.top_offset,
                         ],
-                        tex_coord: [
-                            allocation.min.x as f32 / atlas_size,
-                            (allocation.min.y as usize + glyph.height) as f32 / atlas_size,
+                        area.z_depth,
+                        color,
+                    ));
+                }
+            }
+            // Glyphs of the preedit text have no own position in the text of the area,
+            // so it is underlined and the caret placed in the laid out text instead
+            let composition = area.ime_composition.as_ref().zip(preedit);
+            let visual_glyphs: Vec<LogicalGlyph> = match composition {
+                Some(_) => laid_out_glyphs
+                    .iter()
+                    .map(|glyph| LogicalGlyph {
+                        offset: glyph.byte_offset,
+                        rtl: false,
+                    })
+                    .collect(),
+                None => Vec::new(),
+            };
+            if let Some((_, preedit)) = &composition {
+                let underlined = range_rects(
+                    &laid_out_glyphs,
+                    &lines,
+                    &visual_glyphs,
+                    font,
+                    size,
+                    preedit,
+                );
+                // A dotted line along the bottom of every line of the preedit text
+                for [x0, _, x1, y1] in underlined {
+                    let mut x = x0;
+                    while x < x1 {
+                        rect_vertices.extend_from_slice(&RectVertex::quad(
+                            [
+                                x + area.left_offset,
+                                y1 - IME_UNDERLINE_DOT + area.top_offset,
+                                (x + IME_UNDERLINE_DOT).min(x1) + area.left_offset,
+                                y1 + area.top_offset,
+                            ],
+                            area.z_depth,
+                            area.ime_color,
+                        ));
+                        x += IME_UNDERLINE_DOT * 2.0;
+                    }
+                }
+            }
+            let cursor_position = match &composition {
+                Some((composition, preedit)) => Some(preedit.start + composition.cursor),
+                None => area.cursor_position,
+            };
+            if let (Some(cursor_position), true) = (cursor_position, self.cursor_blink_visible) {
+                let caret_glyphs = match composition {
+                    Some(_) => &visual_glyphs,
+                    None => &logical_glyphs,
+                };
+                // Vertical text has a horizontal caret
+                let [x0, y0, x1, y1] = match &vertical {
+                    Some(vertical) => {
+                        vertical.caret_rect(&logical_glyphs, cursor_position, area.cursor_width)
+                    }
+                    None => {
+                        let [x, top, bottom] = caret_position(
+                            &laid_out_glyphs,
+                            &lines,
+                            caret_glyphs,
+                            font,
+                            size,
+                            cursor_position,
+                            [area_x, area_y],
+                        );
+                        [x, top, x + area.cursor_width, bottom]
+                    }
+                };
+                rect_vertices.extend_from_slice(&RectVertex::quad(
+                    [
+                        x0 + area.left_offset,
+                        y0 + area.top_offset,
+                        x1 + area.left_offset,
+                        y1 + area.top_offset,
+                    ],
+                    area.z_depth,
+                    area.cursor_color,
+                ));
+            }
+            // A thumb on the right edge of the area if the text is taller than the area
+            if area.show_scrollbar && vertical.is_none() {
+                // Same as `text_area_content_height`, the glyphs are laid out at the area and scrolled
+                let content_height = laid_out_glyphs
+                    .iter()
+                    .map(|glyph| glyph.y + glyph.height as f32 + area.scroll_y - area_y)
+                    .fold(0.0, f32::max);
+                if content_height > area_height {
+                    let thumb_height = (area_height * area_height / content_height)
+                        .max(area.scrollbar_width)
+                        .min(area_height);
+                    let scrolled = (area.scroll_y / (content_height - area_height)).clamp(0.0, 1.0);
+                    let thumb_y = area_y + scrolled * (area_height - thumb_height);
+                    let x1 = area_x + area_width;
+                    rect_vertices.extend_from_slice(&RectVertex::quad(
+                        [
+                            x1 - area.scrollbar_width,
+                            thumb_y,
+                            x1,
+                            thumb_y + thumb_height,
                         ],
-                    },
-                ]);
+                        area.z_depth,
+                        area.scrollbar_color,
+                    ));
+                }
+            }
+            // The opacity of the glyphs is already applied by `glyph_quad`
+            if area.opacity != 1.0 {
+                for vertex in &mut rect_vertices {
+                    vertex.color[3] *= area.opacity;
+                }
+            }
+            let vertex_data: &[u8] = bytemuck::cast_slice(&vertices);
+            reserve_vertex_buffer(
+                device,
+                &mut entry.vertex_buffer,
+                "Text Area Vertex Buffer",
+                vertex_data.len() as u64,
+            );
+            self.staging_pool
+                .push(handle, StagedBuffer::Glyphs, vertex_data);
+            entry.vertex_count = vertices.len() as u32;
+            let shadow_vertex_data: &[u8] = bytemuck::cast_slice(&shadow_vertices);
+            reserve_vertex_buffer(
+                device,
+                &mut entry.shadow_vertex_buffer,
+                "Text Area Shadow Vertex Buffer",
+                shadow_vertex_data.len() as u64,
+            );
+            self.staging_pool
+                .push(handle, StagedBuffer::Shadows, shadow_vertex_data);
+            entry.shadow_vertex_count = shadow_vertices.len() as u32;
+            if let Some(quad_indices) = &mut self.quad_indices {
+                let vertex_count = entry.vertex_count.max(entry.shadow_vertex_count);
+                quad_indices.reserve(device, vertex_count / 4);
             }
-            let new_vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Text Area Vertex Buffer"),
-                contents: bytemuck::cast_slice(&vertices),
-                usage: wgpu::BufferUsages::VERTEX,
+            entry.glyph_rects = glyph_rects;
+            entry.glyphs = glyphs;
+            self.debug_show_glyph_bounds_lines = None;
+            let rect_vertex_data: &[u8] = bytemuck::cast_slice(&rect_vertices);
+            reserve_vertex_buffer(
+                device,
+                &mut entry.rect_vertex_buffer,
+                "Text Area Rect Vertex Buffer",
+                rect_vertex_data.len() as u64,
+            );
+            self.staging_pool
+                .push(handle, StagedBuffer::Rects, rect_vertex_data);
+            entry.rect_vertex_count = rect_vertices.len() as u32;
+        }
+        let text_areas = &self.text_areas;
+        self.staging_pool.flush(device, queue, |handle, target| {
+            let entry = text_areas.get(&handle)?;
+            match target {
+                StagedBuffer::Glyphs => entry.vertex_buffer.as_ref(),
+                StagedBuffer::Rects => entry.rect_vertex_buffer.as_ref(),
+                StagedBuffer::Shadows => entry.shadow_vertex_buffer.as_ref(),
+            }
+        });
+        self.atlas.update_mipmaps(device, queue);
+        self.instance_groups
+            .update(device, queue, &self.text_areas, self.window_size);
+
+        // Any render target may be drawn to afterwards
+        for pipelines in std::iter::once(&mut self.pipelines).chain(&mut self.render_targets) {
+            for entry in self.text_areas.values() {
+                if let (None, Some(blend_mode)) = (entry.area.material, entry.area.blend_mode) {
+                    pipelines.prepare_blend_mode::<V>(device, &self.pipeline_sources, blend_mode);
+                }
+            }
+        }
+        if self.debug_show_area_borders && self.debug_show_area_borders_lines.is_none() {
+            let rects = self.text_areas.values().map(|entry| {
+                let [x, y, width, height] = entry.area.pixel_rect(self.window_size);
+                [x, y, x + width, y + height]
             });
-            *vertex_buffer = Some(new_vertex_buffer);
+            self.debug_show_area_borders_lines = DebugLines::new(
+                device,
+                "Debug Show Area Borders",
+                rects,
+                self.debug_border_color,
+            );
         }
+        // Borders follow the size of auto sized areas
+        if stats.dirty_areas_processed > 0 {
+            self.area_borders = None;
+        }
+        if self.area_borders.is_none() {
+            let borders = self
+                .text_areas
+                .values()
+                .filter(|entry| entry.area.visible)
+                .filter_map(|entry| {
+                    let [x, y, width, height] = entry.area.pixel_rect(self.window_size);
+                    Some(([x, y, x + width, y + height], entry.area.border?))
+                });
+            self.area_borders = DebugLines::borders(device, borders);
+        }
+        if self.debug_show_glyph_bounds && self.debug_show_glyph_bounds_lines.is_none() {
+            let rects = self
+                .text_areas
+                .values()
+                .flat_map(|entry| entry.glyph_rects.iter().copied());
+            self.debug_show_glyph_bounds_lines =
+                DebugLines::new(device, "Debug Show Glyph Bounds", rects, [0, 255, 0, 255]);
+        }
+        self.last_frame_stats.set(stats);
+    }
 
-        // Show text areas
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_bind_group(0, &self.atlas.texture_bind_group, &[]);
-        render_pass.set_bind_group(1, &self.meta_info_buffer_bind_group, &[]);
-        for (_, (_, vertex_buffer)) in self.text_areas.iter() {
-            if let Some(vertex_buffer) = vertex_buffer {
-                render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
-                render_pass.draw(
-                    0..(vertex_buffer.size() / std::mem::size_of::<GlyphVertex>() as u64) as u32,
-                    0..1,
+    /// Draws all text areas as of the last `prepare` call into `render_pass`.
+    pub fn draw(&self, render_pass: &mut RenderPass) {
+        self.draw_with(render_pass, None);
+    }
+
+    /// Like `draw`, but into a color target of the format of `target`.
+    pub fn draw_to_target(&self, render_pass: &mut RenderPass, target: RenderTargetHandle) {
+        self.draw_with(render_pass, Some(target));
+    }
+
+    fn draw_with(&self, render_pass: &mut RenderPass, target: Option<RenderTargetHandle>) {
+        // Adds to the counters of `prepare`
+        let mut stats = self.last_frame_stats.get();
+        let pipelines = match target {
+            Some(target) => &self.render_targets[target.id as usize],
+            None => &self.pipelines,
+        };
+        let default_blend_mode = self.pipeline_sources.blend_mode;
+
+        // Borders below all text areas
+        if let Some(borders) = &self.area_borders {
+            render_pass.set_pipeline(&pipelines.area_borders);
+            if self.pipeline_sources.push_constants {
+                render_pass.set_push_constants(
+                    wgpu::ShaderStages::VERTEX,
+                    0,
+                    bytemuck::bytes_of(&self.meta_info),
                 );
+            } else {
+                render_pass.set_bind_group(0, &self.meta_info_buffer_bind_group, &[]);
+            }
+            borders.draw(render_pass, &mut stats);
+        }
+
+        // Show text areas
+        render_pass.set_pipeline(&pipelines.blended(default_blend_mode).render);
+        // Index of the area info bind group, which takes the place of the meta info bind group
+        // if the window size is a push constant
+        let area_info_index = if self.pipeline_sources.push_constants {
+            render_pass.set_push_constants(
+                wgpu::ShaderStages::VERTEX,
+                0,
+                bytemuck::bytes_of(&self.meta_info),
+            );
+            1
+        } else {
+            render_pass.set_bind_group(1, &self.meta_info_buffer_bind_group, &[]);
+            2
+        };
+        let mut entries: Vec<_> = self.text_areas.iter().collect();
+        match &self.draw_order {
+            Some(draw_order) => entries.sort_by(|(_, a), (_, b)| draw_order(&a.area, &b.area)),
+            // Areas with the same material are drawn in one go
+            None => entries.sort_by_key(|(_, entry)| entry.area.material),
+        }
+        for (&handle, entry) in entries {
+            if !entry.area.visible || self.instance_groups.is_follower(handle) {
+                continue;
+            }
+            let Some(atlas_bind_group) = self.atlas.bind_group((entry.area.font, entry.font_face))
+            else {
+                continue;
+            };
+            if let (Some(vertex_buffer), Some(area_info_bind_group)) =
+                (&entry.vertex_buffer, &entry.area_info_bind_group)
+            {
+                let area_pipelines = match entry.area.material {
+                    Some(material) => pipelines.material(material),
+                    None => pipelines.blended(entry.area.blend_mode.unwrap_or(default_blend_mode)),
+                };
+                render_pass.set_pipeline(&area_pipelines.render);
+                render_pass.set_bind_group(0, atlas_bind_group, &[]);
+                render_pass.set_bind_group(area_info_index, area_info_bind_group, &[]);
+                if let (Some(rect_vertex_buffer), 1..) =
+                    (&entry.rect_vertex_buffer, entry.rect_vertex_count)
+                {
+                    render_pass.set_pipeline(&area_pipelines.rect);
+                    render_pass.set_vertex_buffer(0, rect_vertex_buffer.slice(..));
+                    render_pass.draw(0..entry.rect_vertex_count, 0..1);
+                    stats.draw_calls += 1;
+                    stats.total_vertices += entry.rect_vertex_count as u64;
+                    render_pass.set_pipeline(&area_pipelines.render);
+                }
+                if let (Some(shadow_vertex_buffer), 1..) =
+                    (&entry.shadow_vertex_buffer, entry.shadow_vertex_count)
+                {
+                    render_pass.set_vertex_buffer(0, shadow_vertex_buffer.slice(..));
+                    draw_glyph_quads(
+                        render_pass,
+                        self.quad_indices.as_ref(),
+                        entry.shadow_vertex_count,
+                        0..1,
+                        &mut stats,
+                    );
+                }
+                if let (Some(instances), Some(instance_buffer)) = (
+                    self.instance_groups.leader_instances(handle),
+                    self.instance_groups.buffer(),
+                ) {
+                    render_pass.set_pipeline(&area_pipelines.instance_render);
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                    draw_glyph_quads(
+                        render_pass,
+                        self.quad_indices.as_ref(),
+                        entry.vertex_count,
+                        instances,
+                        &mut stats,
+                    );
+                    render_pass.set_pipeline(&area_pipelines.render);
+                } else if entry.vertex_count > 0 {
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    draw_glyph_quads(
+                        render_pass,
+                        self.quad_indices.as_ref(),
+                        entry.vertex_count,
+                        0..1,
+                        &mut stats,
+                    );
+                }
             }
         }
 
         // DEBUG DRAW AREA BORDERS
         if self.debug_show_area_borders {
-            if self.debug_show_area_borders_vertex_buffer.is_none() {
-                let mut vertices = Vec::new();
-                let mut indices = Vec::new();
-                // Create vertex buffer
-                for (i, (area, _)) in self.text_areas.values().enumerate() {
-                    vertices.extend_from_slice(&[
-                        DebugLineVertex {
-                            pos: [area.x, area.y],
-                        },
-                        DebugLineVertex {
-                            pos: [area.x + area.width, area.y],
-                        },
-                        DebugLineVertex {
-                            pos: [area.x + area.width, area.y + area.height],
-                        },
-                        DebugLineVertex {
-                            pos: [area.x, area.y + area.height],
-                        },
-                    ]);
-                    let i = i as u32 * 4;
-                    indices.extend_from_slice(&[i, i + 1, i + 1, i + 2, i + 2, i + 3, i + 3, i]);
+            if let Some(lines) = &self.debug_show_area_borders_lines {
+                render_pass.set_pipeline(&pipelines.debug_show_area_borders);
+                if self.pipeline_sources.push_constants {
+                    render_pass.set_push_constants(
+                        wgpu::ShaderStages::VERTEX,
+                        0,
+                        bytemuck::bytes_of(&self.meta_info),
+                    );
+                } else {
+                    render_pass.set_bind_group(0, &self.meta_info_buffer_bind_group, &[]);
                 }
-                let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Debug Show Area Borders Vertex Buffer"),
-                    contents: bytemuck::cast_slice(&vertices),
-                    usage: wgpu::BufferUsages::VERTEX,
-                });
-                let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                    label: Some("Debug Show Area Borders Index Buffer"),
-                    contents: bytemuck::cast_slice(&indices),
-                    usage: wgpu::BufferUsages::INDEX,
-                });
-                self.debug_show_area_borders_vertex_buffer = Some(vertex_buffer);
-                self.debug_show_area_borders_vertex_count = vertices.len() as u32;
-                self.debug_show_area_borders_index_buffer = Some(index_buffer);
-                self.debug_show_area_borders_index_count = indices.len() as u32;
-            }
-            render_pass.set_pipeline(&self.debug_show_area_borders_pipeline);
-            render_pass.set_bind_group(0, &self.meta_info_buffer_bind_group, &[]);
-            render_pass.set_vertex_buffer(
-                0,
-                self.debug_show_area_borders_vertex_buffer
-                    .as_ref()
-                    .unwrap()
-                    .slice(..),
-            );
-            render_pass.set_index_buffer(
-                self.debug_show_area_borders_index_buffer
-                    .as_ref()
-                    .unwrap()
-                    .slice(..),
-                wgpu::IndexFormat::Uint32,
-            );
-            render_pass.draw_indexed(
-                0..self.debug_show_area_borders_index_count,
-                0,
-                0..self.debug_show_area_borders_vertex_count,
-            );
+                lines.draw(render_pass, &mut stats);
+            }
+        }
+
+        // DEBUG DRAW GLYPH BOUNDS
+        if self.debug_show_glyph_bounds {
+            if let Some(lines) = &self.debug_show_glyph_bounds_lines {
+                render_pass.set_pipeline(&pipelines.debug_show_area_borders);
+                if self.pipeline_sources.push_constants {
+                    render_pass.set_push_constants(
+                        wgpu::ShaderStages::VERTEX,
+                        0,
+                        bytemuck::bytes_of(&self.meta_info),
+                    );
+                } else {
+                    render_pass.set_bind_group(0, &self.meta_info_buffer_bind_group, &[]);
+                }
+                lines.draw(render_pass, &mut stats);
+            }
         }
 
         // DEBUG SHOW ATLAS
         if self.debug_show_atlas {
-            render_pass.set_pipeline(&self.debug_show_atlas_pipeline);
-            render_pass.set_bind_group(0, &self.atlas.texture_bind_group, &[]);
-            render_pass.draw(0..4, 0..1);
+            render_pass.set_pipeline(&pipelines.debug_show_atlas);
+            // Show all atlas pages side by side
+            let pages: Vec<_> = self.atlas.bind_groups().collect();
+            let [window_width, window_height] = self.window_size;
+            let page_width = window_width as f32 / pages.len().max(1) as f32;
+            for (i, bind_group) in pages.into_iter().enumerate() {
+                render_pass.set_viewport(
+                    i as f32 * page_width,
+                    0.0,
+                    page_width,
+                    window_height as f32,
+                    0.0,
+                    1.0,
+                );
+                render_pass.set_bind_group(0, bind_group, &[]);
+                render_pass.draw(0..4, 0..1);
+                stats.draw_calls += 1;
+                stats.total_vertices += 4;
+            }
+            render_pass.set_viewport(
+                0.0,
+                0.0,
+                window_width as f32,
+                window_height as f32,
+                0.0,
+                1.0,
+            );
+        }
+        self.last_frame_stats.set(stats);
+    }
+
+    /// Returns the counters collected during the last `prepare` call and the draws after it.
+    pub fn last_frame_stats(&self) -> RenderStats {
+        self.last_frame_stats.get()
+    }
+}
+
+// Pushes the two triangles of a glyph, its shadow or its outline, converted to the vertex format.
+// Only the 4 corners are pushed if `indexed`, see `QuadIndices`
+fn push_glyph_quad<F: Eq + Hash + Copy, V: VertexFormat>(
+    vertices: &mut Vec<V>,
+    indexed: bool,
+    glyph: &GlyphInfo<F>,
+    rect: [f32; 4],
+    tex_rect: [f32; 4],
+    mut color: [f32; 4],
+    area: &TextArea<F>,
+) {
+    color[3] *= area.opacity;
+    let quad = GlyphVertex::quad(rect, tex_rect, area.z_depth, color);
+    let quad = quad.map(|vertex| V::from_glyph(glyph, vertex));
+    match indexed {
+        true => vertices.extend_from_slice(&[quad[0], quad[1], quad[2], quad[5]]),
+        false => vertices.extend_from_slice(&quad),
+    }
+}
+
+// Draws glyph quads from the vertex buffer in slot 0, indexed if `quad_indices` is set
+fn draw_glyph_quads(
+    render_pass: &mut RenderPass,
+    quad_indices: Option<&QuadIndices>,
+    vertices: u32,
+    instances: Range<u32>,
+    stats: &mut RenderStats,
+) {
+    let instance_count = instances.len() as u64;
+    match quad_indices {
+        Some(quad_indices) => {
+            stats.draw_calls += quad_indices.draw(render_pass, vertices, instances);
+            stats.total_vertices += (vertices / 4 * 6) as u64 * instance_count;
+        }
+        None => {
+            render_pass.draw(0..vertices, instances);
+            stats.draw_calls += 1;
+            stats.total_vertices += vertices as u64 * instance_count;
+        }
+    }
+}
+
+// Makes sure `buffer` can hold `size` bytes, only creating a new buffer if it is too small
+fn reserve_vertex_buffer(
+    device: &Device,
+    buffer: &mut Option<wgpu::Buffer>,
+    label: &str,
+    size: u64,
+) {
+    let size = wgpu::util::align_to(size.max(1), wgpu::COPY_BUFFER_ALIGNMENT);
+    if buffer.as_ref().is_none_or(|buffer| buffer.size() < size) {
+        *buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size,
+            usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        }));
+    }
+}
+
+// Lays out the text of `area` at (x, y) with the font size `size`, dimensions that are `None`
+// are unbounded. `visual_text` replaces the text if it was reordered for bidirectional text.
+#[allow(clippy::too_many_arguments)]
+fn layout_text<F: Eq + Hash + Copy>(
+    layout: &mut Layout,
+    font: &Font,
+    area: &TextArea<F>,
+    size: f32,
+    visual_text: Option<&str>,
+    x: f32,
+    y: f32,
+    max_width: Option<f32>,
+    max_height: Option<f32>,
+) {
+    let text = visual_text.unwrap_or(&area.text);
+    let wrap_style = match area.overflow_wrap {
+        OverflowWrap::Normal => WrapStyle::Word,
+        OverflowWrap::BreakAll => WrapStyle::Letter,
+        // Falls back to letter wrapping if word wrapping would leave a word sticking out
+        OverflowWrap::BreakWord => {
+            let too_wide = |word: &str| {
+                let width: f32 = word
+                    .chars()
+                    .map(|c| font.metrics(c, size).advance_width)
+                    .sum();
+                max_width.is_some_and(|max_width| width > max_width)
+            };
+            match text.split_whitespace().any(too_wide) {
+                true => WrapStyle::Letter,
+                false => WrapStyle::Word,
+            }
         }
+    };
+    layout.reset(&LayoutSettings {
+        x,
+        y,
+        max_width,
+        max_height,
+        horizontal_align: area.h_align,
+        vertical_align: area.v_align,
+        line_height: area.line_height_factor,
+        wrap_style,
+        wrap_hard_breaks: true,
+    });
+    layout.append(
+        &[font],
+        &TextStyle {
+            text,
+            px: size,
+            font_index: 0,
+            user_data: (),
+        },
+    );
+}
+
+// Glyphs and lines of `area` where they are drawn at the font size `size`, or its columns for
+// vertical text
+fn layout_area<F: Eq + Hash + Copy>(
+    layout: &mut Layout,
+    font: &Font,
+    area: &TextArea<F>,
+    size: f32,
+    visual_text: &VisualText,
+    window_size: [u32; 2],
+) -> (
+    Vec<GlyphPosition>,
+    Vec<LinePosition>,
+    Option<VerticalLayout>,
+) {
+    let [area_x, area_y, area_width, area_height] = area.pixel_rect(window_size);
+    let vertical = area.vertical_layout.then(|| {
+        VerticalLayout::new(
+            font,
+            area,
+            size,
+            area_x,
+            area_y,
+            Some(area_width),
+            Some(area_height),
+        )
+    });
+    if vertical.is_none() {
+        layout_text(
+            layout,
+            font,
+            area,
+            size,
+            visual_text.text(),
+            area_x,
+            area_y,
+            Some(area_width),
+            Some(area_height),
+        );
+    }
+    let mut glyphs = match &vertical {
+        Some(vertical) => vertical.glyphs.clone(),
+        None => layout.glyphs().clone(),
+    };
+    let mut lines = layout.lines().cloned().unwrap_or_default();
+    if vertical.is_none() {
+        adjust_layout(&mut glyphs, &mut lines, area, area_x);
+        for glyph in &mut glyphs {
+            glyph.y -= area.scroll_y;
+        }
+        for line in &mut lines {
+            line.baseline_y -= area.scroll_y;
+        }
+    }
+    (glyphs, lines, vertical)
+}
+
+// Binary search steps for the size of `TextArea::shrink_to_fit`
+const SHRINK_TO_FIT_STEPS: u32 = 8;
+
+// Sets the fitted size of `entry` to the largest one between the `min_font_size` of its area
+// and its `size` the text fits into the area at, see `TextAreaEntry::font_size`
+fn shrink_to_fit<F: Eq + Hash + Copy>(
+    layout: &mut Layout,
+    font: &Font,
+    entry: &mut TextAreaEntry<F>,
+    window_size: [u32; 2],
+) {
+    let area = &entry.area;
+    if !area.shrink_to_fit || area.auto_width || area.auto_height {
+        entry.fitted_size = None;
+        return;
+    }
+    let requested = area.size;
+    let [_, _, width, height] = area.pixel_rect(window_size);
+    let mut fits = |size: f32| {
+        let visual_text = VisualText::new(layout, font, area, size, window_size);
+        let [content_width, content_height] = content_size(
+            layout,
+            font,
+            area,
+            size,
+            visual_text.text(),
+            Some(width),
+            None,
+        );
+        content_width <= width && content_height <= height
+    };
+    let min_size = area.min_font_size.min(requested);
+    let size = if min_size == requested || fits(requested) {
+        requested
+    } else {
+        let (mut low, mut high) = (min_size, requested);
+        for _ in 0..SHRINK_TO_FIT_STEPS {
+            let size = (low + high) / 2.0;
+            match fits(size) {
+                true => low = size,
+                false => high = size,
+            }
+        }
+        low
+    };
+    entry.fitted_size = Some((requested, size));
+}
+
+// Width and height of the text of `area` laid out at (0, 0) with the font size `size`
+fn content_size<F: Eq + Hash + Copy>(
+    layout: &mut Layout,
+    font: &Font,
+    area: &TextArea<F>,
+    size: f32,
+    visual_text: Option<&str>,
+    max_width: Option<f32>,
+    max_height: Option<f32>,
+) -> [f32; 2] {
+    if area.vertical_layout {
+        return VerticalLayout::new(font, area, size, 0.0, 0.0, max_width, max_height)
+            .content_size(0.0);
+    }
+    layout_text(
+        layout,
+        font,
+        area,
+        size,
+        visual_text,
+        0.0,
+        0.0,
+        max_width,
+        max_height,
+    );
+    let mut glyphs = layout.glyphs().clone();
+    let mut lines = layout.lines().cloned().unwrap_or_default();
+    adjust_layout(&mut glyphs, &mut lines, area, 0.0);
+    // Include the advance of the last glyph of each line, otherwise it would wrap
+    let content_width = glyphs
+        .iter()
+        .map(|glyph| {
+            let metrics = font.metrics_indexed(glyph.key.glyph_index, size);
+            let advance = glyph.x - metrics.xmin as f32 + metrics.advance_width;
+            advance.max(glyph.x + glyph.width as f32).ceil()
+        })
+        .fold(0.0, f32::max);
+    let content_height = glyphs
+        .iter()
+        .map(|glyph| glyph.y + glyph.height as f32)
+        .fold(0.0, f32::max);
+    [content_width, content_height]
+}
+
+// Applies the indent, tab stops and paragraph spacing of `area`, which fontdue's layout doesn't support,
+// to the laid out glyphs and lines. `origin_x` is the left edge of the layout.
+fn adjust_layout<F: Eq + Hash + Copy>(
+    glyphs: &mut [GlyphPosition],
+    lines: &mut [LinePosition],
+    area: &TextArea<F>,
+    origin_x: f32,
+) {
+    if area.first_line_indent != 0.0 {
+        // The first line and every line after a hard break start a paragraph
+        let mut paragraph_start = true;
+        for line in lines.iter() {
+            if paragraph_start {
+                for glyph in &mut glyphs[line.glyph_start..=line.glyph_end] {
+                    glyph.x += area.first_line_indent;
+                }
+            }
+            paragraph_start = glyphs[line.glyph_end].parent == '\n';
+        }
+    }
+    if area.text.contains('\t') {
+        snap_to_tab_stops(glyphs, lines, origin_x, area.tab_width);
+    }
+    if area.paragraph_spacing != 0.0 {
+        // Every line after a hard break is moved down by the spacing of all breaks above it
+        let mut shift = 0.0;
+        for line in lines {
+            line.baseline_y += shift;
+            for glyph in &mut glyphs[line.glyph_start..=line.glyph_end] {
+                glyph.y += shift;
+            }
+            if glyphs[line.glyph_end].parent == '\n' {
+                shift += area.paragraph_spacing;
+            }
+        }
+    }
+}
+
+// Moves the glyphs behind every tab to the next multiple of `tab_width` from `origin_x`.
+// fontdue gives tabs no width, so lines are still wrapped as if they weren't there.
+fn snap_to_tab_stops(
+    glyphs: &mut [GlyphPosition],
+    lines: &[LinePosition],
+    origin_x: f32,
+    tab_width: f32,
+) {
+    if tab_width <= 0.0 {
+        return;
+    }
+    for line in lines {
+        let mut shift = 0.0;
+        for glyph in &mut glyphs[line.glyph_start..=line.glyph_end] {
+            glyph.x += shift;
+            if glyph.parent == '\t' {
+                let pen_x = glyph.x - origin_x;
+                let tab_stop = ((pen_x / tab_width).floor() + 1.0) * tab_width;
+                shift += tab_stop - pen_x;
+            }
+        }
+    }
+}
+
+// Number of lines of `glyphs` laid out from `text`, see `EasyText::line_count`
+fn count_lines<F>(glyphs: &[GlyphInfo<F>], text: &str) -> usize {
+    let lines = glyphs.last().map_or(0, |glyph| glyph.line + 1);
+    lines.max(1) + (text.ends_with('\n') && lines > 0) as usize
+}
+
+// X coordinates and byte offsets of the caret positions in front of the `glyphs` laid out from
+// `text` on `line`. Behind the last glyph only at the end of the text, or with
+// `Affinity::Upstream` at the end of a wrapped line if `wrapped_ends` is set.
+fn line_carets<F>(
+    glyphs: &[GlyphInfo<F>],
+    text: &str,
+    line: usize,
+    wrapped_ends: bool,
+) -> Option<Vec<(f32, usize, Affinity)>> {
+    let line_count = count_lines(glyphs, text);
+    if line >= line_count {
+        return None;
+    }
+    // Byte offset of every char, and of the end of the text
+    let offsets: Vec<usize> = text
+        .char_indices()
+        .map(|(offset, _)| offset)
+        .chain(std::iter::once(text.len()))
+        .collect();
+    let last_line = line + 1 == line_count;
+    let mut candidates = Vec::new();
+    let mut on_line = glyphs.iter().filter(|glyph| glyph.line == line).peekable();
+    while let Some(glyph) = on_line.next() {
+        let Some(&offset) = offsets.get(glyph.char_index) else {
+            continue;
+        };
+        candidates.push((glyph.x, offset, Affinity::Downstream));
+        if on_line.peek().is_some() {
+            continue;
+        }
+        // Behind the last glyph is only a position of its own at the end of the text,
+        // otherwise it is the start of the next line
+        let end = offsets
+            .get(glyph.char_index + 1)
+            .copied()
+            .unwrap_or(text.len());
+        if last_line {
+            candidates.push((glyph.x + glyph.width, end, Affinity::Downstream));
+        } else if wrapped_ends && !text[offset..].starts_with('\n') {
+            candidates.push((glyph.x + glyph.width, end, Affinity::Upstream));
+        }
+    }
+    Some(candidates)
+}
+
+// Char index of every char boundary in `text`, including its end
+fn char_indices(text: &str) -> Vec<usize> {
+    let mut char_indices = vec![0; text.len() + 1];
+    let mut char_count = 0;
+    for (char_index, (offset, _)) in text.char_indices().enumerate() {
+        char_indices[offset] = char_index;
+        char_count = char_index + 1;
+    }
+    char_indices[text.len()] = char_count;
+    char_indices
+}
+
+// Glyph index ranges of the lines, or of the columns for vertical text
+fn line_ranges(lines: &[LinePosition], vertical: Option<&VerticalLayout>) -> Vec<Range<usize>> {
+    match vertical {
+        Some(vertical) => vertical.columns.clone(),
+        None => lines
+            .iter()
+            .map(|line| line.glyph_start..line.glyph_end + 1)
+            .collect(),
+    }
+}
+
+// Positions of the laid out `glyphs` as returned by `EasyText::glyph_positions`
+fn glyph_infos<F: Eq + Hash + Copy>(
+    area: &TextArea<F>,
+    size: f32,
+    glyphs: &[GlyphPosition],
+    logical_glyphs: &[LogicalGlyph],
+    line_ranges: &[Range<usize>],
+    char_indices: &[usize],
+) -> Vec<GlyphInfo<F>> {
+    let mut line = 0;
+    glyphs
+        .iter()
+        .zip(logical_glyphs)
+        .enumerate()
+        .map(|(i, (glyph, logical))| {
+            while line + 1 < line_ranges.len() && i >= line_ranges[line].end {
+                line += 1;
+            }
+            GlyphInfo {
+                char_index: char_indices[logical.offset],
+                x: glyph.x + area.left_offset,
+                y: glyph.y + area.top_offset,
+                width: glyph.width as f32,
+                height: glyph.height as f32,
+                font: area.font,
+                size,
+                line,
+            }
+        })
+        .collect()
+}
+
+// Rectangles ([min_x, min_y, max_x, max_y]) covering the glyphs of the byte range `range`,
+// one per line
+fn range_rects(
+    glyphs: &[GlyphPosition],
+    lines: &[LinePosition],
+    logical_glyphs: &[LogicalGlyph],
+    font: &Font,
+    size: f32,
+    range: &Range<usize>,
+) -> Vec<[f32; 4]> {
+    let mut rects = Vec::new();
+    for line in lines {
+        let mut min_x = f32::MAX;
+        let mut max_x = f32::MIN;
+        for (glyph, logical) in glyphs[line.glyph_start..=line.glyph_end]
+            .iter()
+            .zip(&logical_glyphs[line.glyph_start..=line.glyph_end])
+        {
+            if !range.contains(&logical.offset) {
+                continue;
+            }
+            let metrics = font.metrics_indexed(glyph.key.glyph_index, size);
+            let pen_x = glyph.x - metrics.xmin as f32;
+            min_x = min_x.min(pen_x);
+            max_x = max_x.max(pen_x + metrics.advance_width);
+        }
+        if min_x < max_x {
+            rects.push([
+                min_x,
+                line.baseline_y - line.max_ascent,
+                max_x,
+                line.baseline_y - line.min_descent,
+            ]);
+        }
+    }
+    rects
+}
+
+// Byte range of the `line`th line of `text` including its line break, lines are only split at
+// line breaks. Empty for the line after a trailing line break.
+fn logical_line_range(text: &str, line: usize) -> Option<Range<usize>> {
+    let start = match line {
+        0 => 0,
+        _ => text.match_indices('\n').nth(line - 1)?.0 + 1,
+    };
+    let end = text[start..]
+        .find('\n')
+        .map_or(text.len(), |end| start + end + 1);
+    Some(start..end)
+}
+
+// Vertical extents ([top, bottom]) of the lines with glyphs of the byte range `range`.
+// Unlike `range_rects`, lines that only hold a line break are included.
+fn line_extents(
+    lines: &[LinePosition],
+    logical_glyphs: &[LogicalGlyph],
+    range: &Range<usize>,
+) -> Vec<[f32; 2]> {
+    lines
+        .iter()
+        .filter(|line| {
+            logical_glyphs[line.glyph_start..=line.glyph_end]
+                .iter()
+                .any(|logical| range.contains(&logical.offset))
+        })
+        .map(|line| {
+            [
+                line.baseline_y - line.max_ascent,
+                line.baseline_y - line.min_descent,
+            ]
+        })
+        .collect()
+}
+
+// Position `[x, top, bottom]` of a caret in front of the character at byte offset `offset`
+// in the laid out `glyphs`. `origin` is used if there are no glyphs.
+// In front means left of left-to-right and right of right-to-left characters.
+fn caret_position(
+    glyphs: &[GlyphPosition],
+    lines: &[LinePosition],
+    logical_glyphs: &[LogicalGlyph],
+    font: &Font,
+    size: f32,
+    offset: usize,
+    origin: [f32; 2],
+) -> [f32; 3] {
+    let line_extent = |line: &LinePosition| {
+        [
+            line.baseline_y - line.max_ascent,
+            line.baseline_y - line.min_descent,
+        ]
+    };
+    // Edge of the glyph at index `i` that is in front of / behind it in reading direction
+    let edge = |i: usize, behind: bool| {
+        let glyph = &glyphs[i];
+        let metrics = font.metrics_indexed(glyph.key.glyph_index, size);
+        let pen_x = glyph.x - metrics.xmin as f32;
+        match logical_glyphs[i].rtl != behind {
+            true => pen_x + metrics.advance_width,
+            false => pen_x,
+        }
+    };
+    // The glyph with the smallest logical offset at or after `offset`
+    let next = (0..glyphs.len())
+        .filter(|&i| logical_glyphs[i].offset >= offset)
+        .min_by_key(|&i| logical_glyphs[i].offset);
+    let last = (0..glyphs.len()).max_by_key(|&i| logical_glyphs[i].offset);
+    let line_of = |i: usize| {
+        lines
+            .iter()
+            .find(|line| (line.glyph_start..=line.glyph_end).contains(&i))
+    };
+    if let Some(i) = next {
+        if let Some(line) = line_of(i) {
+            let [top, bottom] = line_extent(line);
+            return [edge(i, false), top, bottom];
+        }
+    }
+    if let (Some(line), Some(i)) = (lines.last(), last) {
+        let [top, bottom] = line_extent(line);
+        if glyphs[i].parent == '\n' {
+            // At the start of the line following the last hard break
+            let next_top = bottom + line.max_line_gap;
+            return [origin[0], next_top, next_top + bottom - top];
+        }
+        // Behind the last glyph
+        if let Some(line) = line_of(i) {
+            let [top, bottom] = line_extent(line);
+            return [edge(i, true), top, bottom];
+        }
+    }
+    let height = font
+        .horizontal_line_metrics(size)
+        .map_or(size, |metrics| metrics.ascent - metrics.descent);
+    [origin[0], origin[1], origin[1] + height]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A headless `EasyText` with an area of `text` in the m5x7 font, rendered once
+    fn rendered_area(text: &str) -> (EasyText<u8>, TextAreaHandle) {
+        let mut easy_text = EasyText::new_headless(256, 128);
+        easy_text.add_font(0, include_bytes!("../m5x7.ttf").to_vec());
+        let handle = easy_text.add_text_area(TextArea {
+            width: 256.0,
+            height: 128.0,
+            text: text.to_string(),
+            ..TextArea::new(0)
+        });
+        easy_text.render_to_rgba_cpu();
+        (easy_text, handle)
+    }
+
+    // Loads the atlas cache `bytes` into `easy_text` through a file named after the test
+    fn load_cache(
+        easy_text: &mut EasyText<u8>,
+        bytes: &[u8],
+        test: &str,
+    ) -> Result<(), EasyTextError> {
+        let path =
+            std::env::temp_dir().join(format!("easytext-{test}-{}.cache", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        let headless = easy_text.headless.take().unwrap();
+        let result = easy_text.load_atlas_cache(&headless.device, &headless.queue, &path);
+        easy_text.headless = Some(headless);
+        std::fs::remove_file(&path).unwrap();
+        result
+    }
+
+    // The atlas cache of an area of `text`, as written by `save_atlas_cache`
+    fn saved_cache(text: &str, test: &str) -> Vec<u8> {
+        let (easy_text, _) = rendered_area(text);
+        let path =
+            std::env::temp_dir().join(format!("easytext-{test}-{}.cache", std::process::id()));
+        easy_text.save_atlas_cache(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn atlas_cache_round_trip() {
+        let bytes = saved_cache("cached glyphs", "round-trip");
+        let mut easy_text = EasyText::new_headless(256, 128);
+        easy_text.add_font(0, include_bytes!("../m5x7.ttf").to_vec());
+        load_cache(&mut easy_text, &bytes, "round-trip").unwrap();
+        easy_text.add_text_area(TextArea {
+            width: 256.0,
+            height: 128.0,
+            text: "cached glyphs".to_string(),
+            ..TextArea::new(0)
+        });
+        easy_text.render_to_rgba_cpu();
+        let stats = easy_text.last_frame_stats();
+        assert_eq!(stats.dirty_areas_processed, 1);
+        assert_eq!(stats.atlas_uploads, 0);
+    }
+
+    #[test]
+    fn atlas_cache_rejects_truncated_files() {
+        let bytes = saved_cache("cached glyphs", "truncated");
+        let (mut easy_text, _) = rendered_area("");
+        let result = load_cache(&mut easy_text, &bytes[..bytes.len() - 1], "truncated");
+        assert!(matches!(result, Err(EasyTextError::InvalidAtlasCache)));
+    }
+
+    #[test]
+    fn atlas_cache_rejects_wrong_magic_and_version() {
+        let bytes = saved_cache("cached glyphs", "header");
+        let (mut easy_text, _) = rendered_area("");
+        let mut wrong_magic = bytes.clone();
+        wrong_magic[0] = b'X';
+        let result = load_cache(&mut easy_text, &wrong_magic, "header");
+        assert!(matches!(result, Err(EasyTextError::InvalidAtlasCache)));
+        let mut wrong_version = bytes;
+        wrong_version[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        let result = load_cache(&mut easy_text, &wrong_version, "header");
+        assert!(matches!(result, Err(EasyTextError::InvalidAtlasCache)));
+    }
+
+    #[test]
+    fn atlas_cache_rejects_corrupt_bitmap_lengths() {
+        // One 1x1 glyph claiming a bitmap of 4 GiB, followed by a single byte
+        let mut bytes = b"ETAC".to_vec();
+        for value in [1, 256, 1] {
+            bytes.extend_from_slice(&u32::to_le_bytes(value));
+        }
+        bytes.extend_from_slice(&0u64.to_le_bytes());
+        bytes.extend_from_slice(&[0; 4]);
+        bytes.extend_from_slice(&[0; 8]);
+        for value in [1, 1] {
+            bytes.extend_from_slice(&u32::to_le_bytes(value));
+        }
+        bytes.extend_from_slice(&[0; 24]);
+        bytes.extend_from_slice(&u32::MAX.to_le_bytes());
+        bytes.push(0);
+        let (mut easy_text, _) = rendered_area("");
+        let result = load_cache(&mut easy_text, &bytes, "bitmap-len");
+        assert!(matches!(result, Err(EasyTextError::InvalidAtlasCache)));
+    }
+
+    #[test]
+    fn line_queries_use_edited_text() {
+        let (mut easy_text, handle) = rendered_area("hello world\nsecond line");
+        easy_text.text_area_mut(handle).unwrap().text = "hi".to_string();
+        assert_eq!(easy_text.line_count(handle), Some(1));
+        assert_eq!(easy_text.nearest_char_on_line(handle, 0, 1000.0), Some(2));
+        assert_eq!(easy_text.nearest_char_on_line(handle, 1, 0.0), None);
+    }
+
+    #[test]
+    fn caret_from_click_uses_edited_text() {
+        let (mut easy_text, handle) = rendered_area("first line\nsecond line\nthird line");
+        easy_text.text_area_mut(handle).unwrap().text = "hi".to_string();
+        let caret = easy_text.caret_from_click(handle, 1000.0, 1000.0).unwrap();
+        assert_eq!(caret.byte_offset, 2);
+        assert_eq!(caret.line, 0);
+    }
+
+    #[test]
+    fn find_text_positions_uses_edited_text() {
+        let (mut easy_text, handle) = rendered_area("needle");
+        easy_text.text_area_mut(handle).unwrap().text =
+            "a long prefix, then the needle".to_string();
+        let ranges = easy_text.find_text_positions(handle, "needle");
+        let glyphs = easy_text.current_glyphs(handle).unwrap();
+        let needle_x = glyphs
+            .iter()
+            .find(|glyph| glyph.char_index == 24)
+            .unwrap()
+            .x;
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].start_x, needle_x);
+    }
+
+    #[test]
+    fn shrink_to_fit_keeps_the_size() {
+        let mut easy_text = EasyText::<u8>::new_headless(256, 128);
+        easy_text.add_font(0, include_bytes!("../m5x7.ttf").to_vec());
+        let handle = easy_text.add_text_area(TextArea {
+            width: 64.0,
+            height: 16.0,
+            text: "text that doesn't fit".to_string(),
+            size: 32.0,
+            shrink_to_fit: true,
+            ..TextArea::new(0)
+        });
+        easy_text.render_to_rgba_cpu();
+        assert_eq!(easy_text.text_area(handle).unwrap().size, 32.0);
+        let glyphs = easy_text.glyph_positions(handle).unwrap();
+        assert!(glyphs[0].size < 32.0);
+    }
+
+    #[test]
+    fn shrunk_areas_are_not_instanced_with_unshrunk_ones() {
+        let mut easy_text = EasyText::<u8>::new_headless(256, 128);
+        easy_text.add_font(0, include_bytes!("../m5x7.ttf").to_vec());
+        let area = |y: f32, shrink_to_fit: bool| TextArea {
+            y,
+            width: 64.0,
+            height: 16.0,
+            text: "text that doesn't fit".to_string(),
+            size: 32.0,
+            shrink_to_fit,
+            ..TextArea::new(0)
+        };
+        let handles =
+            easy_text.add_text_areas([area(0.0, false), area(32.0, false), area(64.0, true)]);
+        easy_text.render_to_rgba_cpu();
+        // Equal areas are drawn through the first one
+        assert!(easy_text.instance_groups.is_follower(handles[1]));
+        assert!(!easy_text.instance_groups.is_follower(handles[2]));
+        let size = |handle| easy_text.glyph_positions(handle).unwrap()[0].size;
+        assert_eq!(size(handles[1]), 32.0);
+        assert!(size(handles[2]) < 32.0);
+    }
+
+    #[cfg(feature = "bidi")]
+    #[test]
+    fn reordered_areas_are_not_instanced_with_unreordered_ones() {
+        let mut easy_text = EasyText::<u8>::new_headless(256, 128);
+        easy_text.add_font(0, include_bytes!("../m5x7.ttf").to_vec());
+        let area = |y: f32, bidi: bool| TextArea {
+            y,
+            width: 256.0,
+            height: 16.0,
+            text: "abc \u{5d0}\u{5d1}\u{5d2} def".to_string(),
+            bidi,
+            ..TextArea::new(0)
+        };
+        let handles = easy_text.add_text_areas([area(0.0, false), area(32.0, true)]);
+        easy_text.render_to_rgba_cpu();
+        assert!(!easy_text.instance_groups.is_follower(handles[1]));
+    }
+
+    #[test]
+    fn add_font_family_rejects_invalid_faces() {
+        let mut easy_text = EasyText::<u8>::new_headless(16, 16);
+        let result = easy_text.add_font_family(
+            0,
+            vec![
+                (
+                    400,
+                    FontStyle::Normal,
+                    include_bytes!("../m5x7.ttf").to_vec(),
+                ),
+                (700, FontStyle::Normal, b"not a font".to_vec()),
+            ],
+        );
+        assert!(matches!(result, Err(EasyTextError::InvalidFontData(_))));
+        assert!(!easy_text.is_font_loaded(0));
     }
 }
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use wgpu::{DepthStencilState, ShaderModule};
+
+use crate::BlendMode;
+
+/// Render state of the text areas that use it, see `EasyText::register_material`.
+/// All text areas with the same material share its pipelines and are drawn one after another.
+#[derive(Debug, Clone, Default)]
+pub struct Material {
+    pub blend_mode: BlendMode,
+    /// Depth testing of the glyphs, see `EasyTextBuilder::depth_stencil`
+    pub depth_stencil: Option<DepthStencilState>,
+    /// Replaces the glyph shader, see `EasyTextBuilder::custom_shader` for the interface it has to provide.
+    /// With the `push_constants` feature it has to use the push constant bindings described there.
+    pub custom_shader: Option<Arc<ShaderModule>>,
+}
+
+/// Refers to a material registered with `EasyText::register_material`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct MaterialHandle {
+    pub(crate) id: u32,
+}
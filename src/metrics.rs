@@ -0,0 +1,110 @@
+use fontdue::{
+    layout::{CoordinateSystem, Layout, LayoutSettings, TextStyle, WrapStyle},
+    Font,
+};
+
+/// Metrics of a font at a specific size, in pixels. See `EasyText::font_metrics`.
+#[derive(Debug, Clone, Copy)]
+pub struct FontMetricsResult<'a> {
+    /// Distance from the baseline to the top of the highest glyphs
+    pub ascent: f32,
+    /// Distance from the baseline to the bottom of the lowest glyphs, usually negative
+    pub descent: f32,
+    /// Recommended gap between the descent of one line and the ascent of the next
+    pub line_gap: f32,
+    /// Height of capital letters above the baseline, measured on 'H'
+    pub cap_height: f32,
+    /// Height of lowercase letters above the baseline, measured on 'x'
+    pub x_height: f32,
+    font: &'a Font,
+    size: f32,
+}
+
+impl<'a> FontMetricsResult<'a> {
+    pub(crate) fn new(font: &'a Font, size: f32) -> Self {
+        // Fonts without horizontal metrics fall back to the font size as line height
+        let (ascent, descent, line_gap) = font
+            .horizontal_line_metrics(size)
+            .map(|m| (m.ascent, m.descent, m.line_gap))
+            .unwrap_or((size, 0.0, 0.0));
+        let glyph_top = |c: char| {
+            let metrics = font.metrics(c, size);
+            metrics.ymin as f32 + metrics.height as f32
+        };
+        Self {
+            ascent,
+            descent,
+            line_gap,
+            cap_height: glyph_top('H'),
+            x_height: glyph_top('x'),
+            font,
+            size,
+        }
+    }
+
+    /// The horizontal advance of `c`, i.e. how far the pen moves after drawing it.
+    pub fn advance_width_for_char(&self, c: char) -> f32 {
+        self.font.metrics(c, self.size).advance_width
+    }
+}
+
+/// How `EasyText::layout_metrics` lays out the text, all sizes are in pixels.
+// No Debug, fontdue's WrapStyle doesn't implement it
+#[derive(Clone, Copy, PartialEq)]
+pub struct LayoutQuerySettings {
+    pub max_width: f32,
+    pub max_height: f32,
+    pub size: f32,
+    /// Factor for the line height, like `TextArea::line_height_factor`
+    pub line_height: f32,
+    pub wrap_style: WrapStyle,
+}
+
+/// Size of a laid out text, see `EasyText::layout_metrics`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LayoutMetrics {
+    pub line_count: u32,
+    pub content_width: f32,
+    pub content_height: f32,
+    /// True if the text is higher than `max_height`, or a word is wider than `max_width`
+    pub overflow: bool,
+}
+
+impl LayoutMetrics {
+    pub(crate) fn new(font: &Font, text: &str, settings: &LayoutQuerySettings) -> Self {
+        let mut layout = Layout::new(CoordinateSystem::PositiveYDown);
+        layout.reset(&LayoutSettings {
+            max_width: Some(settings.max_width),
+            max_height: Some(settings.max_height),
+            line_height: settings.line_height,
+            wrap_style: settings.wrap_style,
+            ..LayoutSettings::default()
+        });
+        layout.append(
+            &[font],
+            &TextStyle {
+                text,
+                px: settings.size,
+                font_index: 0,
+                user_data: (),
+            },
+        );
+        // Include the advance of the last glyph of each line, like auto sized text areas
+        let content_width = layout
+            .glyphs()
+            .iter()
+            .map(|glyph| {
+                let metrics = font.metrics_indexed(glyph.key.glyph_index, settings.size);
+                let advance = glyph.x - metrics.xmin as f32 + metrics.advance_width;
+                advance.max(glyph.x + glyph.width as f32).ceil()
+            })
+            .fold(0.0, f32::max);
+        let content_height = layout.height();
+        Self {
+            line_count: layout.lines().map_or(0, |lines| lines.len() as u32),
+            content_width,
+            content_height,
+            overflow: content_width > settings.max_width || content_height > settings.max_height,
+        }
+    }
+}
@@ -0,0 +1,522 @@
+use std::{borrow::Cow, collections::HashMap, hash::Hash};
+
+use wgpu::{
+    BindGroupLayout, DepthStencilState, Device, PipelineLayout, PipelineLayoutDescriptor,
+    PushConstantRange, RenderPipeline, RenderPipelineDescriptor, ShaderModule, ShaderStages,
+    TextureFormat,
+};
+
+use crate::{
+    atlas::subpixel_enabled, instancing::InstanceOffset, BlendMode, DebugLineVertex,
+    EasyTextBuilder, Material, MaterialHandle, MetaInfo, RectVertex, VertexFormat,
+    GLYPH_FRAGMENT_ENTRY_POINT,
+};
+
+// The window size is pushed as the whole `MetaInfo` struct
+const META_INFO_PUSH_CONSTANT_RANGE: PushConstantRange = PushConstantRange {
+    stages: ShaderStages::VERTEX,
+    range: 0..std::mem::size_of::<MetaInfo>() as u32,
+};
+
+// Declarations of the meta info and area info bindings of the built-in glyph and rect shaders
+fn bindings_source(push_constants: bool) -> &'static str {
+    if push_constants {
+        include_str!("./shaders/bindings_push_constants.wgsl")
+    } else {
+        include_str!("./shaders/bindings.wgsl")
+    }
+}
+
+/// Shaders and pipeline layouts shared by the pipelines of all render targets.
+pub(crate) struct PipelineSources {
+    debug_show_atlas_shader: ShaderModule,
+    debug_show_atlas_pipeline_layout: PipelineLayout,
+    debug_show_area_borders_shader: ShaderModule,
+    debug_show_area_borders_pipeline_layout: PipelineLayout,
+    // Compiled by every `MaterialPipelines`, see there
+    shader_source: String,
+    rect_shader_source: String,
+    render_pipeline_layout: PipelineLayout,
+    depth_stencil: Option<DepthStencilState>,
+    sample_count: u32,
+    // Blend mode of text areas without `TextArea::blend_mode`
+    pub blend_mode: BlendMode,
+    // Whether the window size is passed as a push constant instead of the meta info bind group
+    pub push_constants: bool,
+    // Whether glyphs are drawn with `fs_subpixel`, see `atlas::subpixel_enabled`
+    subpixel: bool,
+}
+
+impl PipelineSources {
+    pub fn new<F: Eq + Hash + Copy, V: VertexFormat>(
+        device: &Device,
+        atlas_bind_group_layout: &BindGroupLayout,
+        meta_info_bind_group_layout: &BindGroupLayout,
+        area_info_bind_group_layout: &BindGroupLayout,
+        builder: &EasyTextBuilder<F, V>,
+    ) -> Self {
+        let push_constants = cfg!(feature = "push_constants")
+            && device.features().contains(wgpu::Features::PUSH_CONSTANTS);
+        let subpixel = subpixel_enabled(device);
+        let push_constant_ranges: &[PushConstantRange] = if push_constants {
+            &[META_INFO_PUSH_CONSTANT_RANGE]
+        } else {
+            &[]
+        };
+        let meta_info_bind_group_layouts: &[&BindGroupLayout] = if push_constants {
+            &[]
+        } else {
+            &[meta_info_bind_group_layout]
+        };
+
+        let debug_show_atlas_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("EasyText Debug Show Atlas Shader"),
+            source: wgpu::ShaderSource::Wgsl(
+                include_str!("./shaders/debug_show_atlas.wgsl").into(),
+            ),
+        });
+        let debug_show_atlas_pipeline_layout =
+            device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("EasyText Debug Show Atlas Pipeline Layout"),
+                bind_group_layouts: &[atlas_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let debug_show_area_borders_shader =
+            device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                label: Some("EasyText Debug Show TextArea Borders Shader"),
+                source: wgpu::ShaderSource::Wgsl(
+                    if push_constants {
+                        include_str!("./shaders/debug_show_area_borders_push_constants.wgsl")
+                    } else {
+                        include_str!("./shaders/debug_show_area_borders.wgsl")
+                    }
+                    .into(),
+                ),
+            });
+        let debug_show_area_borders_pipeline_layout =
+            device.create_pipeline_layout(&PipelineLayoutDescriptor {
+                label: Some("EasyText Debug Show TextArea Borders Pipeline Layout"),
+                bind_group_layouts: meta_info_bind_group_layouts,
+                push_constant_ranges,
+            });
+        // Custom shaders are used as they are, see `EasyTextBuilder::custom_shader`
+        let shader_source = match builder.custom_shader.as_deref().or(V::shader()) {
+            Some(shader) => shader.to_string(),
+            None if subpixel => {
+                bindings_source(push_constants).to_string()
+                    + include_str!("./shaders/shader.wgsl")
+                    + include_str!("./shaders/subpixel.wgsl")
+            }
+            None => {
+                bindings_source(push_constants).to_string() + include_str!("./shaders/shader.wgsl")
+            }
+        };
+        let rect_shader_source =
+            bindings_source(push_constants).to_string() + include_str!("./shaders/rect.wgsl");
+        let render_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("EasyText Atlas Render Pipeline Layout"),
+            bind_group_layouts: &[
+                &[atlas_bind_group_layout],
+                meta_info_bind_group_layouts,
+                &[area_info_bind_group_layout],
+            ]
+            .concat(),
+            push_constant_ranges,
+        });
+        Self {
+            debug_show_atlas_shader,
+            debug_show_atlas_pipeline_layout,
+            debug_show_area_borders_shader,
+            debug_show_area_borders_pipeline_layout,
+            shader_source,
+            rect_shader_source,
+            render_pipeline_layout,
+            depth_stencil: builder.depth_stencil.clone(),
+            sample_count: builder.sample_count,
+            blend_mode: builder.blend_mode,
+            push_constants,
+            subpixel,
+        }
+    }
+}
+
+/// All render pipelines needed to draw into a color target of one `TextureFormat`.
+pub(crate) struct Pipelines {
+    format: TextureFormat,
+    // Created for the blend mode of the builder right away and for others when first used
+    blend_pipelines: HashMap<BlendMode, MaterialPipelines>,
+    // Indexed by `MaterialHandle`
+    materials: Vec<MaterialPipelines>,
+    pub debug_show_atlas: RenderPipeline,
+    pub debug_show_area_borders: RenderPipeline,
+    // Draws `TextArea::border`, with the shader of the debug lines
+    pub area_borders: RenderPipeline,
+}
+
+/// The pipelines that draw text areas, which exist once per blend mode and material.
+pub(crate) struct MaterialPipelines {
+    pub render: RenderPipeline,
+    // Draws text areas with identical vertices at several positions at once
+    pub instance_render: RenderPipeline,
+    // Shares the layout with the glyph pipeline, so the bind groups stay valid when switching
+    pub rect: RenderPipeline,
+}
+
+impl PipelineSources {
+    // The material of text areas without `TextArea::material`
+    fn material(&self, blend_mode: BlendMode) -> Material {
+        Material {
+            blend_mode,
+            depth_stencil: self.depth_stencil.clone(),
+            custom_shader: None,
+        }
+    }
+}
+
+impl Pipelines {
+    pub fn new<V: VertexFormat>(
+        device: &Device,
+        sources: &PipelineSources,
+        format: TextureFormat,
+    ) -> Self {
+        let multisample = wgpu::MultisampleState {
+            count: sources.sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        };
+
+        // DEBUG SHOW ATLAS
+        let debug_show_atlas = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("EasyText Debug Show Atlas Pipeline"),
+            layout: Some(&sources.debug_show_atlas_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &sources.debug_show_atlas_shader,
+                entry_point: "vs_main",
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &sources.debug_show_atlas_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleStrip,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Cw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: sources.depth_stencil.clone(),
+            multisample,
+            multiview: None,
+            cache: None,
+        });
+
+        // Vertices with a color only, drawn as lines by the debug overlays and as triangles by
+        // the area borders
+        let colored_pipeline = |label, topology, blend| {
+            device.create_render_pipeline(&RenderPipelineDescriptor {
+                label: Some(label),
+                layout: Some(&sources.debug_show_area_borders_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &sources.debug_show_area_borders_shader,
+                    entry_point: "vs_main",
+                    buffers: &[DebugLineVertex::desc()],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &sources.debug_show_area_borders_shader,
+                    entry_point: "fs_main",
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format,
+                        blend: Some(blend),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology,
+                    strip_index_format: None,
+                    front_face: wgpu::FrontFace::Cw,
+                    cull_mode: None,
+                    polygon_mode: wgpu::PolygonMode::Fill,
+                    unclipped_depth: false,
+                    conservative: false,
+                },
+                depth_stencil: sources.depth_stencil.clone(),
+                multisample,
+                multiview: None,
+                cache: None,
+            })
+        };
+
+        // DEBUG DRAW AREA BORDERS
+        let debug_show_area_borders = colored_pipeline(
+            "EasyText Debug Show TextArea Borders Pipeline",
+            wgpu::PrimitiveTopology::LineList,
+            wgpu::BlendState::REPLACE,
+        );
+        let area_borders = colored_pipeline(
+            "EasyText Area Borders Pipeline",
+            wgpu::PrimitiveTopology::TriangleList,
+            wgpu::BlendState::ALPHA_BLENDING,
+        );
+
+        let blend_pipelines = HashMap::from([(
+            sources.blend_mode,
+            MaterialPipelines::new::<V>(
+                device,
+                sources,
+                format,
+                &sources.material(sources.blend_mode),
+            ),
+        )]);
+        Self {
+            format,
+            blend_pipelines,
+            materials: Vec::new(),
+            debug_show_atlas,
+            debug_show_area_borders,
+            area_borders,
+        }
+    }
+
+    /// Creates the pipelines for `blend_mode` if they don't exist yet.
+    pub fn prepare_blend_mode<V: VertexFormat>(
+        &mut self,
+        device: &Device,
+        sources: &PipelineSources,
+        blend_mode: BlendMode,
+    ) {
+        if !self.blend_pipelines.contains_key(&blend_mode) {
+            let material = sources.material(blend_mode);
+            let pipelines = MaterialPipelines::new::<V>(device, sources, self.format, &material);
+            self.blend_pipelines.insert(blend_mode, pipelines);
+        }
+    }
+
+    /// The pipelines for `blend_mode`, which have to be created with `prepare_blend_mode` first.
+    pub fn blended(&self, blend_mode: BlendMode) -> &MaterialPipelines {
+        &self.blend_pipelines[&blend_mode]
+    }
+
+    /// Creates the pipelines for the next registered material.
+    pub fn add_material<V: VertexFormat>(
+        &mut self,
+        device: &Device,
+        sources: &PipelineSources,
+        material: &Material,
+    ) {
+        let pipelines = MaterialPipelines::new::<V>(device, sources, self.format, material);
+        self.materials.push(pipelines);
+    }
+
+    pub fn material(&self, material: MaterialHandle) -> &MaterialPipelines {
+        &self.materials[material.id as usize]
+    }
+}
+
+impl MaterialPipelines {
+    fn new<V: VertexFormat>(
+        device: &Device,
+        sources: &PipelineSources,
+        format: TextureFormat,
+        material: &Material,
+    ) -> Self {
+        // Blending of the glyph and rect pipelines, the shaders premultiply their output with an
+        // override constant, which is only set if needed so custom shaders don't have to declare it
+        let premultiplied = HashMap::from([("premultiplied_alpha".to_string(), 1.0)]);
+        let (blend, constants) = match material.blend_mode {
+            BlendMode::StraightAlpha => (wgpu::BlendState::ALPHA_BLENDING, HashMap::new()),
+            BlendMode::PremultipliedAlpha => (
+                wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING,
+                premultiplied,
+            ),
+            // Adds the premultiplied color to the target, the alpha is blended as usual
+            BlendMode::Additive => (
+                wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::One,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::OVER,
+                },
+                premultiplied,
+            ),
+        };
+        // The subpixel shader always outputs premultiplied colors, with the coverage of every
+        // channel as second source to blend the target with
+        let (glyph_blend, glyph_entry_point) = match (sources.subpixel, material.blend_mode) {
+            (false, _) => (blend, GLYPH_FRAGMENT_ENTRY_POINT),
+            (true, BlendMode::StraightAlpha | BlendMode::PremultipliedAlpha) => (
+                wgpu::BlendState {
+                    color: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrc1,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    alpha: wgpu::BlendComponent::OVER,
+                },
+                "fs_subpixel",
+            ),
+            // Pipelines of dual source shaders have to use the second source, its alpha is
+            // the same as the alpha of the color
+            (true, BlendMode::Additive) => (
+                wgpu::BlendState {
+                    alpha: wgpu::BlendComponent {
+                        src_factor: wgpu::BlendFactor::One,
+                        dst_factor: wgpu::BlendFactor::OneMinusSrc1Alpha,
+                        operation: wgpu::BlendOperation::Add,
+                    },
+                    ..blend
+                },
+                "fs_subpixel",
+            ),
+        };
+        let compilation_options = wgpu::PipelineCompilationOptions {
+            constants: &constants,
+            ..Default::default()
+        };
+        let multisample = wgpu::MultisampleState {
+            count: sources.sample_count,
+            mask: !0,
+            alpha_to_coverage_enabled: false,
+        };
+        // The shader modules are not shared between blend modes, because the GL backend of
+        // wgpu caches linked programs by module and entry point, ignoring override constants
+        let own_shader;
+        let shader = match &material.custom_shader {
+            Some(shader) => shader.as_ref(),
+            None => {
+                own_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+                    label: Some("EasyText Shader"),
+                    source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(&sources.shader_source)),
+                });
+                &own_shader
+            }
+        };
+        let rect_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("EasyText Rect Shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(&sources.rect_shader_source)),
+        });
+
+        // RENDER PIPELINE
+        let render = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("EasyText Render Pipeline"),
+            layout: Some(&sources.render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_main",
+                buffers: &[V::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: glyph_entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(glyph_blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: compilation_options.clone(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Cw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: material.depth_stencil.clone(),
+            multisample,
+            multiview: None,
+            cache: None,
+        });
+
+        let instance_render = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("EasyText Instanced Render Pipeline"),
+            layout: Some(&sources.render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: "vs_instanced",
+                buffers: &[V::desc(), InstanceOffset::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: glyph_entry_point,
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(glyph_blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: compilation_options.clone(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Cw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: material.depth_stencil.clone(),
+            multisample,
+            multiview: None,
+            cache: None,
+        });
+
+        // RECT PIPELINE
+        let rect = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("EasyText Rect Pipeline"),
+            layout: Some(&sources.render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &rect_shader,
+                entry_point: "vs_main",
+                buffers: &[RectVertex::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &rect_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(blend),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: compilation_options.clone(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Cw,
+                cull_mode: None,
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: material.depth_stencil.clone(),
+            multisample,
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            render,
+            instance_render,
+            rect,
+        }
+    }
+}
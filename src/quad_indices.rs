@@ -0,0 +1,63 @@
+use std::ops::Range;
+
+use wgpu::{util::DeviceExt, Buffer, Device, RenderPass};
+
+// u16 indices can address 16384 quads of 4 vertices, longer texts are drawn in several calls
+const MAX_QUADS: u32 = (u16::MAX as u32 + 1) / 4;
+
+/// Index buffer shared by the glyph quads of all text areas if
+/// `EasyTextBuilder::use_index_buffer` is set, every quad then only needs 4 vertices.
+pub(crate) struct QuadIndices {
+    buffer: Option<Buffer>,
+    quads: u32,
+}
+
+impl QuadIndices {
+    pub fn new() -> Self {
+        Self {
+            buffer: None,
+            quads: 0,
+        }
+    }
+
+    /// Makes sure the buffer holds the indices of at least `quads` quads,
+    /// only creating a new buffer if it is too small.
+    pub fn reserve(&mut self, device: &Device, quads: u32) {
+        let quads = quads.min(MAX_QUADS);
+        if quads <= self.quads {
+            return;
+        }
+        let quads = quads.next_power_of_two().min(MAX_QUADS);
+        let indices: Vec<u16> = (0..quads as u16)
+            .flat_map(|quad| {
+                let i = quad * 4;
+                [i, i + 1, i + 2, i, i + 2, i + 3]
+            })
+            .collect();
+        self.buffer = Some(
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("EasyText Quad Index Buffer"),
+                contents: bytemuck::cast_slice(&indices),
+                usage: wgpu::BufferUsages::INDEX,
+            }),
+        );
+        self.quads = quads;
+    }
+
+    /// Draws the quads of the `vertices` bound to slot 0, 4 per quad.
+    /// Returns the number of draw calls.
+    pub fn draw(&self, render_pass: &mut RenderPass, vertices: u32, instances: Range<u32>) -> u32 {
+        let Some(buffer) = &self.buffer else {
+            return 0;
+        };
+        render_pass.set_index_buffer(buffer.slice(..), wgpu::IndexFormat::Uint16);
+        let quads = vertices / 4;
+        let mut draw_calls = 0;
+        for first in (0..quads).step_by(MAX_QUADS as usize) {
+            let count = (quads - first).min(MAX_QUADS);
+            render_pass.draw_indexed(0..count * 6, (first * 4) as i32, instances.clone());
+            draw_calls += 1;
+        }
+        draw_calls
+    }
+}
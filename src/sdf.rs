@@ -0,0 +1,129 @@
+use fontdue::{Font, Metrics};
+
+// Glyphs are rasterized once at this size and scaled to the size of the text area
+pub(crate) const SDF_BASE_SIZE: f32 = 48.0;
+
+// Squared distances are "infinite" when they exceed this
+const INF: f32 = 1e20;
+
+/// Settings of the signed distance field atlas.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SdfSettings {
+    /// Distance in pixels (at the base size) the field extends beyond the glyph outline.
+    /// Larger values allow wider outlines and shadows but use more atlas space.
+    pub spread: f32,
+}
+
+impl Default for SdfSettings {
+    fn default() -> Self {
+        Self { spread: 8.0 }
+    }
+}
+
+/// Rasterizes a glyph at `SDF_BASE_SIZE` and converts it to a signed distance field.
+/// The returned metrics include the padding of `spread` pixels on each side.
+/// The outline of the glyph is at 0.5 (128), values above are inside.
+pub(crate) fn rasterize_sdf(
+    font: &Font,
+    glyph_index: u16,
+    settings: &SdfSettings,
+) -> (Metrics, Vec<u8>) {
+    let (mut metrics, coverage) = font.rasterize_indexed(glyph_index, SDF_BASE_SIZE);
+    if metrics.width == 0 || metrics.height == 0 {
+        return (metrics, coverage);
+    }
+    let pad = settings.spread.ceil() as usize;
+    let width = metrics.width + pad * 2;
+    let height = metrics.height + pad * 2;
+
+    // Squared distances to the nearest outside (outer) and inside (inner) pixel,
+    // partially covered pixels are treated as being partway to the outline
+    let mut outer = vec![INF; width * height];
+    let mut inner = vec![0.0; width * height];
+    for y in 0..metrics.height {
+        for x in 0..metrics.width {
+            let a = coverage[y * metrics.width + x] as f32 / 255.0;
+            let i = (y + pad) * width + x + pad;
+            if a >= 1.0 {
+                outer[i] = 0.0;
+                inner[i] = INF;
+            } else if a > 0.0 {
+                outer[i] = (0.5 - a).max(0.0).powi(2);
+                inner[i] = (a - 0.5).max(0.0).powi(2);
+            }
+        }
+    }
+    edt(&mut outer, width, height);
+    edt(&mut inner, width, height);
+
+    let bitmap = outer
+        .iter()
+        .zip(&inner)
+        .map(|(outer, inner)| {
+            let distance = outer.sqrt() - inner.sqrt();
+            ((0.5 - distance / (2.0 * settings.spread)).clamp(0.0, 1.0) * 255.0).round() as u8
+        })
+        .collect();
+    metrics.width = width;
+    metrics.height = height;
+    metrics.xmin -= pad as i32;
+    metrics.ymin -= pad as i32;
+    (metrics, bitmap)
+}
+
+// 2D squared euclidean distance transform, by Felzenszwalb and Huttenlocher
+fn edt(grid: &mut [f32], width: usize, height: usize) {
+    let n = width.max(height);
+    let mut f = vec![0.0; n];
+    let mut d = vec![0.0; n];
+    let mut v = vec![0; n];
+    let mut z = vec![0.0; n + 1];
+    for x in 0..width {
+        for y in 0..height {
+            f[y] = grid[y * width + x];
+        }
+        edt_1d(&f[..height], &mut d, &mut v, &mut z);
+        for y in 0..height {
+            grid[y * width + x] = d[y];
+        }
+    }
+    for y in 0..height {
+        f[..width].copy_from_slice(&grid[y * width..(y + 1) * width]);
+        edt_1d(&f[..width], &mut d, &mut v, &mut z);
+        grid[y * width..(y + 1) * width].copy_from_slice(&d[..width]);
+    }
+}
+
+// Lower envelope of the parabolas rooted at (q, f[q])
+fn edt_1d(f: &[f32], d: &mut [f32], v: &mut [usize], z: &mut [f32]) {
+    let n = f.len();
+    let mut k = 0;
+    v[0] = 0;
+    z[0] = -INF;
+    z[1] = INF;
+    for q in 1..n {
+        let mut s = intersection(f, q, v[k]);
+        // z[0] is -INF, so this always stops at k == 0
+        while s <= z[k] {
+            k -= 1;
+            s = intersection(f, q, v[k]);
+        }
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = INF;
+    }
+    k = 0;
+    for (q, d) in d.iter_mut().enumerate().take(n) {
+        while z[k + 1] < q as f32 {
+            k += 1;
+        }
+        let r = v[k];
+        *d = (q as f32 - r as f32).powi(2) + f[r];
+    }
+}
+
+// Position where the parabolas rooted at q and r intersect
+fn intersection(f: &[f32], q: usize, r: usize) -> f32 {
+    ((f[q] + (q * q) as f32) - (f[r] + (r * r) as f32)) / (2 * (q - r)) as f32
+}
@@ -0,0 +1,17 @@
+// Bindings of shader.wgsl and rect.wgsl, bindings_push_constants.wgsl is used instead if the
+// window size is passed as a push constant
+struct MetaInfo {
+    window_size: vec2<u32>,
+};
+@group(1) @binding(0)
+var<uniform> meta_info: MetaInfo;
+
+struct AreaInfo {
+    transform: mat4x4<f32>,
+    // Bounds of the area in pixels (min_x, min_y, max_x, max_y), only used for rounded corners
+    clip_rect: vec4<f32>,
+    corner_radius: f32,
+};
+@group(2) @binding(0)
+var<uniform> area_info: AreaInfo;
+
@@ -0,0 +1,16 @@
+// Bindings of shader.wgsl and rect.wgsl with the `push_constants` feature. Without the meta info
+// bind group, the area info moves one group down
+struct MetaInfo {
+    window_size: vec2<u32>,
+};
+var<push_constant> meta_info: MetaInfo;
+
+struct AreaInfo {
+    transform: mat4x4<f32>,
+    // Bounds of the area in pixels (min_x, min_y, max_x, max_y), only used for rounded corners
+    clip_rect: vec4<f32>,
+    corner_radius: f32,
+};
+@group(1) @binding(0)
+var<uniform> area_info: AreaInfo;
+
@@ -0,0 +1,35 @@
+// debug_show_area_borders.wgsl with the window size passed as a push constant
+
+// Vertex shader
+struct MetaInfo {
+    window_size: vec2<u32>,
+};
+var<push_constant> meta_info: MetaInfo;
+
+struct VertexInput {
+    @location(0) position: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+};
+
+@vertex
+fn vs_main(
+    input: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let x = input.position.x / f32(meta_info.window_size.x) * 2.0 - 1.0;
+    let y = 1.0 - input.position.y / f32(meta_info.window_size.y) * 2.0;
+    out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+    out.color = input.color;
+    return out;
+}
+
+// Fragment shader
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return in.color;
+}
+ 
\ No newline at end of file
@@ -0,0 +1,52 @@
+// Vertex shader, the bindings are declared in bindings.wgsl
+struct VertexInput {
+    @location(0) position: vec3<f32>,
+    @location(1) color: vec4<f32>,
+};
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    // Position before the transform, for clipping to the rounded corners
+    @location(1) area_position: vec2<f32>,
+};
+
+@vertex
+fn vs_main(
+    input: VertexInput,
+) -> VertexOutput {
+    var out: VertexOutput;
+    let transformed = area_info.transform * vec4<f32>(input.position.xy, 0.0, 1.0);
+    let position = transformed.xy / transformed.w;
+    let x = position.x / f32(meta_info.window_size.x) * 2.0 - 1.0;
+    let y = 1.0 - position.y / f32(meta_info.window_size.y) * 2.0;
+    out.clip_position = vec4<f32>(x, y, input.position.z, 1.0);
+    out.color = input.color;
+    out.area_position = input.position.xy;
+    return out;
+}
+
+// Fragment shader
+// Set for BlendMode::PremultipliedAlpha
+override premultiplied_alpha: bool = false;
+
+// Coverage of the pixel at `position` by the area with rounded corners, 1 without a corner radius
+fn corner_coverage(position: vec2<f32>) -> f32 {
+    let radius = area_info.corner_radius;
+    if radius <= 0.0 {
+        return 1.0;
+    }
+    let rect = area_info.clip_rect;
+    let half_size = (rect.zw - rect.xy) * 0.5;
+    let q = abs(position - (rect.xy + rect.zw) * 0.5) - half_size + radius;
+    let distance = length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+    return clamp(0.5 - distance, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let alpha = in.color.a * corner_coverage(in.area_position);
+    if premultiplied_alpha {
+        return vec4<f32>(in.color.rgb * alpha, alpha);
+    }
+    return vec4<f32>(in.color.rgb, alpha);
+}
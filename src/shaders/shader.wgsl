@@ -1,32 +1,75 @@
-// Vertex shader
-struct MetaInfo {
-    window_size: vec2<u32>,
-};
-@group(1) @binding(0)
-var<uniform> meta_info: MetaInfo;
-
+// Vertex shader, the bindings are declared in bindings.wgsl
 struct VertexInput {
-    @location(0) position: vec2<f32>,
+    @location(0) position: vec3<f32>,
     @location(1) tex_coords: vec2<f32>,
+    @location(2) color: vec4<f32>,
 };
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    // Position before the transform, for clipping to the rounded corners
+    @location(2) area_position: vec2<f32>,
 };
 
+fn to_clip_space(position: vec3<f32>) -> vec4<f32> {
+    let transformed = area_info.transform * vec4<f32>(position.xy, 0.0, 1.0);
+    let pixel = transformed.xy / transformed.w;
+    let x = pixel.x / f32(meta_info.window_size.x) * 2.0 - 1.0;
+    let y = 1.0 - pixel.y / f32(meta_info.window_size.y) * 2.0;
+    return vec4<f32>(x, y, position.z, 1.0);
+}
+
 @vertex
 fn vs_main(
     input: VertexInput,
 ) -> VertexOutput {
     var out: VertexOutput;
-    let x = input.position.x / f32(meta_info.window_size.x) * 2.0 - 1.0;
-    let y = 1.0 - input.position.y / f32(meta_info.window_size.y) * 2.0;
-    out.clip_position = vec4<f32>(x, y, 0.0, 1.0);
+    out.clip_position = to_clip_space(input.position);
+    out.tex_coords = input.tex_coords;
+    out.color = input.color;
+    out.area_position = input.position.xy;
+    return out;
+}
+
+// Draws the same text area at several positions, offset per instance
+@vertex
+fn vs_instanced(
+    input: VertexInput,
+    @location(3) offset: vec2<f32>,
+) -> VertexOutput {
+    var out: VertexOutput;
+    out.clip_position = to_clip_space(input.position + vec3<f32>(offset, 0.0));
     out.tex_coords = input.tex_coords;
+    out.color = input.color;
+    out.area_position = input.position.xy + offset;
     return out;
 }
 
 // Fragment shader
+// Set for BlendMode::PremultipliedAlpha
+override premultiplied_alpha: bool = false;
+
+fn output_color(color: vec4<f32>) -> vec4<f32> {
+    if premultiplied_alpha {
+        return vec4<f32>(color.rgb * color.a, color.a);
+    }
+    return color;
+}
+
+// Coverage of the pixel at `position` by the area with rounded corners, 1 without a corner radius
+fn corner_coverage(position: vec2<f32>) -> f32 {
+    let radius = area_info.corner_radius;
+    if radius <= 0.0 {
+        return 1.0;
+    }
+    let rect = area_info.clip_rect;
+    let half_size = (rect.zw - rect.xy) * 0.5;
+    let q = abs(position - (rect.xy + rect.zw) * 0.5) - half_size + radius;
+    let distance = length(max(q, vec2<f32>(0.0))) + min(max(q.x, q.y), 0.0) - radius;
+    return clamp(0.5 - distance, 0.0, 1.0);
+}
+
 @group(0) @binding(0)
 var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
@@ -35,8 +78,22 @@ var s_diffuse: sampler;
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let sample = textureSample(t_diffuse, s_diffuse, in.tex_coords);
-    if sample.x < 0.00001 {
+    let alpha = sample.x * corner_coverage(in.area_position);
+    if alpha < 0.00001 {
+        discard;
+    }
+    return output_color(vec4<f32>(in.color.rgb, in.color.a * alpha));
+}
+
+// Fragment shader for distance field atlases, the glyph outline is at 0.5
+@fragment
+fn fs_sdf(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = textureSample(t_diffuse, s_diffuse, in.tex_coords).x;
+    // Antialias over about one pixel on screen, regardless of the scale
+    let width = max(fwidth(distance), 0.0001) * 0.5;
+    let alpha = smoothstep(0.5 - width, 0.5 + width, distance) * corner_coverage(in.area_position);
+    if alpha < 0.00001 {
         discard;
     }
-    return vec4<f32>(1.0, 1.0, 1.0, sample.x);
+    return output_color(vec4<f32>(in.color.rgb, in.color.a * alpha));
 }
@@ -0,0 +1,21 @@
+
+// Fragment shader for subpixel atlases, appended to shader.wgsl with the `subpixel` feature.
+// Every color channel of the target is blended with the coverage of its own subpixel, which
+// needs dual source blending: target = color + target * (1 - coverage)
+struct SubpixelOutput {
+    @location(0) color: vec4<f32>,
+    @location(0) @second_blend_source coverage: vec4<f32>,
+};
+
+@fragment
+fn fs_subpixel(in: VertexOutput) -> SubpixelOutput {
+    let sample = textureSample(t_diffuse, s_diffuse, in.tex_coords) * corner_coverage(in.area_position);
+    if sample.a < 0.00001 {
+        discard;
+    }
+    let coverage = sample.rgb * in.color.a;
+    var out: SubpixelOutput;
+    out.color = vec4<f32>(in.color.rgb * coverage, sample.a * in.color.a);
+    out.coverage = vec4<f32>(coverage, sample.a * in.color.a);
+    return out;
+}
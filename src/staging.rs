@@ -0,0 +1,92 @@
+use wgpu::{Buffer, Device, Queue};
+
+use crate::TextAreaHandle;
+
+// Which vertex buffer of a text area a staged upload is meant for
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum StagedBuffer {
+    Glyphs,
+    Rects,
+    Shadows,
+}
+
+struct StagedCopy {
+    handle: TextAreaHandle,
+    target: StagedBuffer,
+    offset: u64,
+    size: u64,
+}
+
+/// Collects the vertex data of all dirty text areas of a frame, so it can be uploaded
+/// with a single `write_buffer` call and then copied into the vertex buffers on the GPU.
+pub(crate) struct StagingPool {
+    buffer: Option<Buffer>,
+    data: Vec<u8>,
+    copies: Vec<StagedCopy>,
+}
+
+impl StagingPool {
+    pub fn new() -> Self {
+        Self {
+            buffer: None,
+            data: Vec::new(),
+            copies: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, handle: TextAreaHandle, target: StagedBuffer, contents: &[u8]) {
+        if contents.is_empty() {
+            return;
+        }
+        // Copy offsets and sizes have to be multiples of COPY_BUFFER_ALIGNMENT
+        let offset = self.data.len() as u64;
+        self.data.extend_from_slice(contents);
+        let size = wgpu::util::align_to(contents.len() as u64, wgpu::COPY_BUFFER_ALIGNMENT);
+        self.data.resize((offset + size) as usize, 0);
+        self.copies.push(StagedCopy {
+            handle,
+            target,
+            offset,
+            size,
+        });
+    }
+
+    /// Uploads all pushed data and copies it into the buffers returned by `target_buffer`.
+    /// Does nothing if nothing was pushed since the last flush.
+    pub fn flush<'a>(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        mut target_buffer: impl FnMut(TextAreaHandle, StagedBuffer) -> Option<&'a Buffer>,
+    ) {
+        if self.copies.is_empty() {
+            return;
+        }
+        let needed = self.data.len() as u64;
+        // The pool only grows, doubling its size, so it settles after a few frames
+        if self
+            .buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.size() < needed)
+        {
+            self.buffer = Some(device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("EasyText Staging Buffer"),
+                size: needed.next_power_of_two(),
+                usage: wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }));
+        }
+        let buffer = self.buffer.as_ref().unwrap();
+        queue.write_buffer(buffer, 0, &self.data);
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("EasyText Staging Encoder"),
+        });
+        for copy in self.copies.drain(..) {
+            if let Some(target) = target_buffer(copy.handle, copy.target) {
+                encoder.copy_buffer_to_buffer(buffer, copy.offset, target, 0, copy.size);
+            }
+        }
+        queue.submit(std::iter::once(encoder.finish()));
+        self.data.clear();
+    }
+}
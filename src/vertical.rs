@@ -0,0 +1,192 @@
+use std::{hash::Hash, ops::Range};
+
+use fontdue::{
+    layout::{CharacterData, GlyphPosition, GlyphRasterConfig},
+    Font,
+};
+
+use crate::{area::TextArea, LogicalGlyph};
+
+/// Glyphs laid out top to bottom in columns, which go from right to left.
+/// Used instead of fontdue's layout for `TextArea::vertical_layout`.
+pub(crate) struct VerticalLayout {
+    pub glyphs: Vec<GlyphPosition>,
+    // The space every glyph takes up in its column ([min_x, min_y, max_x, max_y])
+    cells: Vec<[f32; 4]>,
+    // Glyph index ranges of the columns
+    pub columns: Vec<Range<usize>>,
+    column_width: f32,
+    // Top right corner of the first column
+    origin: [f32; 2],
+}
+
+impl VerticalLayout {
+    /// Lays out the text of `area` at `size` with the top right corner of the first column at
+    /// (x + max_width, y), or with the left edge of the last column at x if the width is unbounded.
+    /// A column ends at a line break or when the next glyph would exceed `max_height`.
+    pub fn new<F: Eq + Hash + Copy>(
+        font: &Font,
+        area: &TextArea<F>,
+        size: f32,
+        x: f32,
+        y: f32,
+        max_width: Option<f32>,
+        max_height: Option<f32>,
+    ) -> Self {
+        // Glyphs are drawn upright, centered in a column as wide as a horizontal line is high
+        let horizontal = font.horizontal_line_metrics(size);
+        let ascent = horizontal.map_or(size, |metrics| metrics.ascent);
+        let line_height = horizontal.map_or(size, |metrics| metrics.ascent - metrics.descent);
+        let column_width = font
+            .vertical_line_metrics(size)
+            .or(horizontal)
+            .map_or(size, |metrics| metrics.ascent - metrics.descent)
+            * area.line_height_factor;
+
+        let mut glyphs = Vec::new();
+        let mut cells = Vec::new();
+        let mut columns = Vec::new();
+        let mut column_start = 0;
+        let mut column = 0;
+        let mut pen_y = 0.0;
+        for (byte_offset, c) in area.text.char_indices() {
+            let glyph_index = font.lookup_glyph_index(c);
+            let char_data = CharacterData::classify(c, glyph_index);
+            let metrics = font.metrics_indexed(glyph_index, size);
+            // Fonts without vertical metrics (vmtx) give every glyph a square cell, like
+            // browsers do for upright text
+            let advance = match metrics.advance_height > 0.0 {
+                true => metrics.advance_height,
+                false => line_height,
+            };
+            if !char_data.is_control()
+                && pen_y > 0.0
+                && max_height.is_some_and(|max_height| pen_y + advance > max_height)
+            {
+                columns.push(column_start..glyphs.len());
+                column_start = glyphs.len();
+                column += 1;
+                pen_y = 0.0;
+            }
+            let min_x = -(column as f32 + 1.0) * column_width;
+            let visible = !char_data.is_control();
+            glyphs.push(GlyphPosition {
+                key: GlyphRasterConfig {
+                    glyph_index,
+                    px: size,
+                    font_hash: font.file_hash(),
+                },
+                font_index: 0,
+                parent: c,
+                x: min_x + (column_width - metrics.advance_width) / 2.0 + metrics.xmin as f32,
+                y: pen_y + ascent - (metrics.ymin as f32 + metrics.height as f32),
+                width: if visible { metrics.width } else { 0 },
+                height: if visible { metrics.height } else { 0 },
+                byte_offset,
+                char_data,
+                user_data: (),
+            });
+            if c == '\n' {
+                cells.push([min_x, pen_y, min_x + column_width, pen_y]);
+                columns.push(column_start..glyphs.len());
+                column_start = glyphs.len();
+                column += 1;
+                pen_y = 0.0;
+            } else {
+                cells.push([min_x, pen_y, min_x + column_width, pen_y + advance]);
+                pen_y += advance;
+            }
+        }
+        columns.push(column_start..glyphs.len());
+
+        let offset_x = match max_width {
+            Some(max_width) => x + max_width,
+            None => x + columns.len() as f32 * column_width,
+        };
+        for glyph in &mut glyphs {
+            glyph.x += offset_x;
+            glyph.y += y;
+        }
+        for cell in &mut cells {
+            *cell = [
+                cell[0] + offset_x,
+                cell[1] + y,
+                cell[2] + offset_x,
+                cell[3] + y,
+            ];
+        }
+        Self {
+            glyphs,
+            cells,
+            columns,
+            column_width,
+            origin: [offset_x, y],
+        }
+    }
+
+    /// Width and height of the laid out text, with the layout starting at `y`.
+    pub fn content_size(&self, y: f32) -> [f32; 2] {
+        let min_x = self
+            .cells
+            .iter()
+            .map(|cell| cell[0])
+            .fold(f32::MAX, f32::min);
+        let max_x = self
+            .cells
+            .iter()
+            .map(|cell| cell[2])
+            .fold(f32::MIN, f32::max);
+        let max_y = self.cells.iter().map(|cell| cell[3]).fold(y, f32::max);
+        [(max_x - min_x).max(0.0), max_y - y]
+    }
+
+    /// Rectangles covering the glyphs of the byte range `range`, one per column.
+    pub fn range_rects(
+        &self,
+        logical_glyphs: &[LogicalGlyph],
+        range: &Range<usize>,
+    ) -> Vec<[f32; 4]> {
+        self.columns
+            .iter()
+            .filter_map(|column| {
+                column
+                    .clone()
+                    .filter(|&i| range.contains(&logical_glyphs[i].offset))
+                    .map(|i| self.cells[i])
+                    .reduce(|a, b| {
+                        [
+                            a[0].min(b[0]),
+                            a[1].min(b[1]),
+                            a[2].max(b[2]),
+                            a[3].max(b[3]),
+                        ]
+                    })
+            })
+            .collect()
+    }
+
+    /// Rectangle of a horizontal caret `thickness` pixels high above the character
+    /// at byte offset `offset`.
+    pub fn caret_rect(
+        &self,
+        logical_glyphs: &[LogicalGlyph],
+        offset: usize,
+        thickness: f32,
+    ) -> [f32; 4] {
+        let [origin_x, origin_y] = self.origin;
+        let next = (0..self.glyphs.len())
+            .filter(|&i| logical_glyphs[i].offset >= offset)
+            .min_by_key(|&i| logical_glyphs[i].offset);
+        let [x0, y, x1] = match (next, self.cells.last(), self.glyphs.last()) {
+            (Some(i), _, _) => [self.cells[i][0], self.cells[i][1], self.cells[i][2]],
+            // At the top of the column following the last hard break
+            (None, Some(cell), Some(glyph)) if glyph.parent == '\n' => {
+                [cell[0] - self.column_width, origin_y, cell[0]]
+            }
+            // Below the last glyph
+            (None, Some(cell), _) => [cell[0], cell[3], cell[2]],
+            (None, None, _) => [origin_x - self.column_width, origin_y, origin_x],
+        };
+        [x0, y, x1, y + thickness]
+    }
+}